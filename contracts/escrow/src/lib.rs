@@ -1,23 +1,2161 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, vec, Env, String, Vec};
+use soroban_sdk::{
+    contract, contracterror, contractevent, contractimpl, contracttype, token, Address, BytesN,
+    Env, String, Vec,
+};
+
+const MAX_MILESTONES: u32 = 20;
+const MAX_EVIDENCE_ENTRIES: u32 = 10;
+const MAX_BENEFICIARIES: u32 = 10;
+const DEFAULT_EVIDENCE_WINDOW: u64 = 3 * 24 * 60 * 60;
+const DEFAULT_APPEAL_WINDOW: u64 = 2 * 24 * 60 * 60;
+const DEFAULT_VOTING_WINDOW: u64 = 7 * 24 * 60 * 60;
+const CANCEL_PROPOSAL_TTL: u64 = 24 * 60 * 60;
+const DEFAULT_APPEAL_BOND_BPS: u32 = 1_000;
+const DEFAULT_PANEL_SIZE: u32 = 3;
+const BPS_DENOMINATOR: i128 = 10_000;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum DataKey {
+    Admin,
+    Escrow(u64),
+    EscrowCounter,
+    Dispute(u64),
+    Arbitrators,
+    Milestones(u64),
+    Evidence(u64),
+    EvidenceWindow,
+    Appeal(u64),
+    AppealConfig,
+    Arbitrator(Address),
+    ArbitratorConfig,
+    NextArbitrator,
+    Beneficiaries(u64),
+    CancelProposal(u64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum EscrowStatus {
+    Locked,
+    Released,
+    Disputed,
+    Ruled,
+    Appealed,
+    Resolved,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum MilestoneStatus {
+    Pending,
+    Released,
+    Disputed,
+    Resolved,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum AssignmentMode {
+    RoundRobin,
+    Random,
+}
+
+#[contracterror]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    NotInitialized = 1,
+    AlreadyInitialized = 2,
+    Unauthorized = 3,
+    InvalidAmount = 4,
+    EscrowNotFound = 5,
+    InvalidStatus = 6,
+    AlreadyResolved = 7,
+    NotArbitrator = 8,
+    InvalidShares = 9,
+    ReleaseNotDue = 10,
+    DisputeWindowClosed = 11,
+    InvalidMilestones = 12,
+    MilestoneNotFound = 13,
+    MilestoneOutOfOrder = 14,
+    MilestoneEscrow = 15,
+    DisputeNotFound = 16,
+    EvidenceWindowOpen = 17,
+    EvidenceWindowClosed = 18,
+    EvidenceLimitReached = 19,
+    InvalidConfig = 20,
+    AppealWindowOpen = 21,
+    AppealWindowClosed = 22,
+    NotLosingParty = 23,
+    NotEnoughArbitrators = 24,
+    NotPanelMember = 25,
+    AlreadyVoted = 26,
+    StakeTooLow = 27,
+    AlreadyRegistered = 28,
+    NotAssignedArbitrator = 29,
+    ArbitratorBusy = 30,
+    RegistryNotConfigured = 31,
+    InvalidSplit = 32,
+    ProposalNotFound = 33,
+    ProposalExpired = 34,
+    VotingClosed = 35,
+    VotingOpen = 36,
+    ConflictOfInterest = 37,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowCreated {
+    pub escrow_id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub release_after: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowReleased {
+    pub escrow_id: u64,
+    pub merchant: Address,
+    pub amount: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowDisputed {
+    pub escrow_id: u64,
+    pub disputed_by: Address,
+    pub evidence_hash: BytesN<32>,
+    pub disputed_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvidenceSubmitted {
+    pub escrow_id: u64,
+    pub submitted_by: Address,
+    pub content_hash: BytesN<32>,
+    pub uri: String,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EvidenceFinalized {
+    pub escrow_id: u64,
+    pub finalized_by: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisputeResolved {
+    pub escrow_id: u64,
+    pub resolved_by: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+    pub resolved_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MilestoneReleased {
+    pub escrow_id: u64,
+    pub index: u32,
+    pub approved_by: Address,
+    pub amount: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MilestoneDisputed {
+    pub escrow_id: u64,
+    pub index: u32,
+    pub disputed_by: Address,
+    pub evidence_hash: BytesN<32>,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MilestoneResolved {
+    pub escrow_id: u64,
+    pub index: u32,
+    pub resolved_by: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppealFiled {
+    pub escrow_id: u64,
+    pub appellant: Address,
+    pub bond: i128,
+    pub panel: Vec<Address>,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppealVoteCast {
+    pub escrow_id: u64,
+    pub arbitrator: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppealDecided {
+    pub escrow_id: u64,
+    pub overturned: bool,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RulingFinalized {
+    pub escrow_id: u64,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorAdded {
+    pub arbitrator: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorRemoved {
+    pub arbitrator: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorRegistered {
+    pub arbitrator: Address,
+    pub stake: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorSlashed {
+    pub arbitrator: Address,
+    pub amount: i128,
+    pub remaining_stake: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorAssigned {
+    pub escrow_id: u64,
+    pub arbitrator: Address,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArbitratorFeePaid {
+    pub escrow_id: u64,
+    pub arbitrator: Address,
+    pub amount: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BeneficiaryPaid {
+    pub escrow_id: u64,
+    pub beneficiary: Address,
+    pub amount: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitUpdated {
+    pub escrow_id: u64,
+    pub updated_by: Address,
+    pub beneficiaries: Vec<Beneficiary>,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CancelProposed {
+    pub escrow_id: u64,
+    pub proposed_by: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+    pub expires_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowCancelled {
+    pub escrow_id: u64,
+    pub accepted_by: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Escrow {
+    pub id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub status: EscrowStatus,
+    pub created_at: u64,
+    pub release_after: u64,
+    pub milestone_count: u32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub struct Beneficiary {
+    pub address: Address,
+    pub bps: u32,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct MilestoneTerms {
+    pub amount: i128,
+    pub description_hash: BytesN<32>,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Milestone {
+    pub amount: i128,
+    pub description_hash: BytesN<32>,
+    pub status: MilestoneStatus,
+    pub released_amount: i128,
+    pub refunded_amount: i128,
+    pub arbitrator: Option<Address>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct Evidence {
+    pub submitted_by: Address,
+    pub content_hash: BytesN<32>,
+    pub uri: String,
+    pub timestamp: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct MilestoneProgress {
+    pub released: i128,
+    pub refunded: i128,
+    pub outstanding: i128,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Dispute {
+    pub escrow_id: u64,
+    pub disputed_by: Address,
+    pub evidence_hash: BytesN<32>,
+    pub disputed_at: u64,
+    pub evidence_deadline: u64,
+    pub customer_finalized: bool,
+    pub merchant_finalized: bool,
+    pub resolved_by: Option<Address>,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+    pub resolved_at: Option<u64>,
+    pub appeal_deadline: u64,
+    pub arbitrator: Option<Address>,
+    pub fee: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct CancelProposal {
+    pub proposed_by: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+    pub proposed_at: u64,
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct AppealConfig {
+    pub window: u64,
+    pub bond_bps: u32,
+    pub panel_size: u32,
+    pub voting_window: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PanelVote {
+    pub arbitrator: Address,
+    pub merchant_share: i128,
+    pub customer_share: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitratorConfig {
+    pub stake_token: Address,
+    pub min_stake: i128,
+    pub fee_bps: u32,
+    pub assignment: AssignmentMode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitratorProfile {
+    pub stake: i128,
+    pub resolved_count: u32,
+    pub overturned_count: u32,
+    pub open_cases: u32,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Appeal {
+    pub escrow_id: u64,
+    pub appellant: Address,
+    pub bond: i128,
+    pub panel: Vec<Address>,
+    pub votes: Vec<PanelVote>,
+    pub filed_at: u64,
+    pub voting_deadline: u64,
+}
 
 #[contract]
-pub struct Contract;
-
-// This is a sample contract. Replace this placeholder with your own contract logic.
-// A corresponding test example is available in `test.rs`.
-//
-// For comprehensive examples, visit <https://github.com/stellar/soroban-examples>.
-// The repository includes use cases for the Stellar ecosystem, such as data storage on
-// the blockchain, token swaps, liquidity pools, and more.
-//
-// Refer to the official documentation:
-// <https://developers.stellar.org/docs/build/smart-contracts/overview>.
+pub struct EscrowContract;
+
 #[contractimpl]
-impl Contract {
-    pub fn hello(env: Env, to: String) -> Vec<String> {
-        vec![&env, String::from_str(&env, "Hello"), to]
+impl EscrowContract {
+    pub fn initialize(env: Env, admin: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&DataKey::Admin) {
+            return Err(Error::AlreadyInitialized);
+        }
+
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        Ok(())
+    }
+
+    pub fn add_arbitrator(env: Env, arbitrator: Address) -> Result<(), Error> {
+        // Require admin authentication
+        require_admin(&env)?;
+
+        let mut arbitrators = get_arbitrators(&env);
+        if !arbitrators.contains(&arbitrator) {
+            arbitrators.push_back(arbitrator.clone());
+            env.storage()
+                .instance()
+                .set(&DataKey::Arbitrators, &arbitrators);
+        }
+
+        // Admin-appointed arbitrators serve without a stake until a registry is configured
+        save_profile(&env, &arbitrator, &load_profile(&env, &arbitrator));
+
+        ArbitratorAdded { arbitrator }.publish(&env);
+
+        Ok(())
+    }
+
+    pub fn remove_arbitrator(env: Env, arbitrator: Address) -> Result<(), Error> {
+        // Require admin authentication
+        require_admin(&env)?;
+
+        let mut arbitrators = get_arbitrators(&env);
+        let index = arbitrators
+            .first_index_of(&arbitrator)
+            .ok_or(Error::NotArbitrator)?;
+        arbitrators.remove(index);
+        env.storage()
+            .instance()
+            .set(&DataKey::Arbitrators, &arbitrators);
+
+        ArbitratorRemoved { arbitrator }.publish(&env);
+
+        Ok(())
+    }
+
+    pub fn is_arbitrator(env: Env, address: Address) -> bool {
+        get_arbitrators(&env).contains(&address)
     }
+
+    pub fn get_arbitrators(env: Env) -> Vec<Address> {
+        get_arbitrators(&env)
+    }
+
+    pub fn set_arbitrator_config(env: Env, config: ArbitratorConfig) -> Result<(), Error> {
+        // Require admin authentication
+        require_admin(&env)?;
+
+        if config.min_stake < 0 || config.fee_bps as i128 > BPS_DENOMINATOR {
+            return Err(Error::InvalidConfig);
+        }
+
+        // Stakes already posted are held in the original token
+        if let Ok(current) = Self::get_arbitrator_config(env.clone()) {
+            if current.stake_token != config.stake_token {
+                return Err(Error::InvalidConfig);
+            }
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ArbitratorConfig, &config);
+
+        Ok(())
+    }
+
+    pub fn get_arbitrator_config(env: Env) -> Result<ArbitratorConfig, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::ArbitratorConfig)
+            .ok_or(Error::RegistryNotConfigured)
+    }
+
+    pub fn get_arbitrator_profile(
+        env: Env,
+        arbitrator: Address,
+    ) -> Result<ArbitratorProfile, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Arbitrator(arbitrator))
+            .ok_or(Error::NotArbitrator)
+    }
+
+    pub fn register_arbitrator(env: Env, arbitrator: Address, stake: i128) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        let config = Self::get_arbitrator_config(env.clone())?;
+
+        let mut arbitrators = get_arbitrators(&env);
+        if arbitrators.contains(&arbitrator) {
+            return Err(Error::AlreadyRegistered);
+        }
+
+        // Whatever is left from an earlier registration counts towards the minimum
+        if stake < 0 {
+            return Err(Error::InvalidAmount);
+        }
+        let mut profile = load_profile(&env, &arbitrator);
+        let total = profile
+            .stake
+            .checked_add(stake)
+            .ok_or(Error::InvalidAmount)?;
+        if total < config.min_stake {
+            return Err(Error::StakeTooLow);
+        }
+
+        if stake > 0 {
+            token::Client::new(&env, &config.stake_token).transfer(
+                &arbitrator,
+                env.current_contract_address(),
+                &stake,
+            );
+        }
+
+        profile.stake = total;
+        save_profile(&env, &arbitrator, &profile);
+
+        arbitrators.push_back(arbitrator.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::Arbitrators, &arbitrators);
+
+        ArbitratorRegistered {
+            arbitrator,
+            stake: total,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn unregister_arbitrator(env: Env, arbitrator: Address) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        let mut profile = Self::get_arbitrator_profile(env.clone(), arbitrator.clone())?;
+
+        // Assigned disputes must be ruled on before the stake can leave
+        if profile.open_cases > 0 {
+            return Err(Error::ArbitratorBusy);
+        }
+
+        let mut arbitrators = get_arbitrators(&env);
+        if let Some(index) = arbitrators.first_index_of(&arbitrator) {
+            arbitrators.remove(index);
+            env.storage()
+                .instance()
+                .set(&DataKey::Arbitrators, &arbitrators);
+        }
+
+        if profile.stake > 0 {
+            let config = Self::get_arbitrator_config(env.clone())?;
+            token::Client::new(&env, &config.stake_token).transfer(
+                &env.current_contract_address(),
+                &arbitrator,
+                &profile.stake,
+            );
+        }
+
+        // The case record is kept so reputation survives re-registration
+        profile.stake = 0;
+        save_profile(&env, &arbitrator, &profile);
+
+        ArbitratorRemoved { arbitrator }.publish(&env);
+
+        Ok(())
+    }
+
+    pub fn slash_arbitrator(env: Env, arbitrator: Address, amount: i128) -> Result<(), Error> {
+        // Require admin authentication
+        let admin = require_admin(&env)?;
+
+        let config = Self::get_arbitrator_config(env.clone())?;
+        let mut profile = Self::get_arbitrator_profile(env.clone(), arbitrator.clone())?;
+        if amount <= 0 || amount > profile.stake {
+            return Err(Error::InvalidAmount);
+        }
+
+        profile.stake -= amount;
+        save_profile(&env, &arbitrator, &profile);
+
+        token::Client::new(&env, &config.stake_token).transfer(
+            &env.current_contract_address(),
+            &admin,
+            &amount,
+        );
+
+        ArbitratorSlashed {
+            arbitrator: arbitrator.clone(),
+            amount,
+            remaining_stake: profile.stake,
+        }
+        .publish(&env);
+
+        // An arbitrator slashed below the minimum stops taking new cases
+        if profile.stake < config.min_stake {
+            let mut arbitrators = get_arbitrators(&env);
+            if let Some(index) = arbitrators.first_index_of(&arbitrator) {
+                arbitrators.remove(index);
+                env.storage()
+                    .instance()
+                    .set(&DataKey::Arbitrators, &arbitrators);
+                ArbitratorRemoved { arbitrator }.publish(&env);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn create_escrow(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        dispute_window: u64,
+    ) -> Result<u64, Error> {
+        // Require customer authentication
+        customer.require_auth();
+
+        // Validate amount is positive
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Funds release on their own once the dispute window has passed
+        let release_after = env
+            .ledger()
+            .timestamp()
+            .checked_add(dispute_window)
+            .ok_or(Error::InvalidAmount)?;
+
+        Ok(open_escrow(
+            &env,
+            &customer,
+            customer.clone(),
+            merchant,
+            token,
+            amount,
+            release_after,
+            0,
+        ))
+    }
+
+    pub fn create_escrow_for(
+        env: Env,
+        depositor: Address,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        dispute_window: u64,
+    ) -> Result<u64, Error> {
+        // The depositor funds the escrow on the customer's behalf, e.g. a payment contract
+        depositor.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        let release_after = env
+            .ledger()
+            .timestamp()
+            .checked_add(dispute_window)
+            .ok_or(Error::InvalidAmount)?;
+
+        Ok(open_escrow(
+            &env,
+            &depositor,
+            customer,
+            merchant,
+            token,
+            amount,
+            release_after,
+            0,
+        ))
+    }
+
+    pub fn create_split_escrow(
+        env: Env,
+        customer: Address,
+        token: Address,
+        amount: i128,
+        dispute_window: u64,
+        beneficiaries: Vec<Beneficiary>,
+    ) -> Result<u64, Error> {
+        // Require customer authentication
+        customer.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        validate_split(&beneficiaries)?;
+
+        let release_after = env
+            .ledger()
+            .timestamp()
+            .checked_add(dispute_window)
+            .ok_or(Error::InvalidAmount)?;
+
+        // The first beneficiary acts as the merchant for claims and disputes
+        let merchant = beneficiaries.get(0).unwrap().address;
+        let escrow_id = open_escrow(
+            &env,
+            &customer,
+            customer.clone(),
+            merchant,
+            token,
+            amount,
+            release_after,
+            0,
+        );
+        env.storage()
+            .instance()
+            .set(&DataKey::Beneficiaries(escrow_id), &beneficiaries);
+
+        Ok(escrow_id)
+    }
+
+    pub fn get_beneficiaries(env: Env, escrow_id: u64) -> Vec<Beneficiary> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Beneficiaries(escrow_id))
+            .unwrap_or(Vec::new(&env))
+    }
+
+    pub fn update_split(
+        env: Env,
+        arbitrator: Address,
+        escrow_id: u64,
+        beneficiaries: Vec<Beneficiary>,
+    ) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        if !get_arbitrators(&env).contains(&arbitrator) {
+            return Err(Error::NotArbitrator);
+        }
+
+        // Only split escrows under dispute can be re-split
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if escrow.status != EscrowStatus::Disputed {
+            return Err(Error::InvalidStatus);
+        }
+        if Self::get_beneficiaries(env.clone(), escrow_id).is_empty() {
+            return Err(Error::InvalidSplit);
+        }
+
+        let dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        check_assigned(&env, &escrow, &dispute.arbitrator, &arbitrator)?;
+        validate_split(&beneficiaries)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::Beneficiaries(escrow_id), &beneficiaries);
+
+        SplitUpdated {
+            escrow_id,
+            updated_by: arbitrator,
+            beneficiaries,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn create_milestone_escrow(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        terms: Vec<MilestoneTerms>,
+    ) -> Result<u64, Error> {
+        // Require customer authentication
+        customer.require_auth();
+
+        if terms.is_empty() || terms.len() > MAX_MILESTONES {
+            return Err(Error::InvalidMilestones);
+        }
+
+        // The escrowed total is the sum of the milestone amounts
+        let mut total: i128 = 0;
+        let mut milestones = Vec::new(&env);
+        for term in terms.iter() {
+            if term.amount <= 0 {
+                return Err(Error::InvalidAmount);
+            }
+            total = total.checked_add(term.amount).ok_or(Error::InvalidAmount)?;
+            milestones.push_back(Milestone {
+                amount: term.amount,
+                description_hash: term.description_hash,
+                status: MilestoneStatus::Pending,
+                released_amount: 0,
+                refunded_amount: 0,
+                arbitrator: None,
+            });
+        }
+
+        // Milestone escrows only release through milestone approvals
+        let escrow_id = open_escrow(
+            &env,
+            &customer,
+            customer.clone(),
+            merchant,
+            token,
+            total,
+            u64::MAX,
+            milestones.len(),
+        );
+        env.storage()
+            .instance()
+            .set(&DataKey::Milestones(escrow_id), &milestones);
+
+        Ok(escrow_id)
+    }
+
+    pub fn get_milestones(env: Env, escrow_id: u64) -> Result<Vec<Milestone>, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Milestones(escrow_id))
+            .ok_or(Error::EscrowNotFound)
+    }
+
+    pub fn get_milestone_progress(env: Env, escrow_id: u64) -> Result<MilestoneProgress, Error> {
+        let mut progress = MilestoneProgress {
+            released: 0,
+            refunded: 0,
+            outstanding: 0,
+        };
+        for milestone in Self::get_milestones(env, escrow_id)?.iter() {
+            match milestone.status {
+                MilestoneStatus::Pending | MilestoneStatus::Disputed => {
+                    progress.outstanding += milestone.amount
+                }
+                MilestoneStatus::Released | MilestoneStatus::Resolved => {
+                    progress.released += milestone.released_amount;
+                    progress.refunded += milestone.refunded_amount;
+                }
+            }
+        }
+        Ok(progress)
+    }
+
+    pub fn approve_milestone(
+        env: Env,
+        caller: Address,
+        escrow_id: u64,
+        index: u32,
+    ) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        let mut milestones = Self::get_milestones(env.clone(), escrow_id)?;
+        let mut milestone = milestones.get(index).ok_or(Error::MilestoneNotFound)?;
+
+        // The customer signs off on a pending milestone; once it is disputed only the
+        // arbitrator assigned to it can approve in their place
+        let assigned = milestone.arbitrator.clone();
+        if caller == escrow.customer {
+            if milestone.status != MilestoneStatus::Pending {
+                return Err(Error::InvalidStatus);
+            }
+        } else {
+            if milestone.status != MilestoneStatus::Disputed
+                || !get_arbitrators(&env).contains(&caller)
+            {
+                return Err(Error::Unauthorized);
+            }
+            check_assigned(&env, &escrow, &assigned, &caller)?;
+        }
+
+        // Milestones are released in order; earlier ones must be settled first
+        for earlier in milestones.iter().take(index as usize) {
+            if earlier.status == MilestoneStatus::Pending
+                || earlier.status == MilestoneStatus::Disputed
+            {
+                return Err(Error::MilestoneOutOfOrder);
+            }
+        }
+
+        let was_disputed = milestone.status == MilestoneStatus::Disputed;
+        milestone.status = MilestoneStatus::Released;
+        milestone.released_amount = milestone.amount;
+        milestones.set(index, milestone.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::Milestones(escrow_id), &milestones);
+
+        token::Client::new(&env, &escrow.token).transfer(
+            &env.current_contract_address(),
+            &escrow.merchant,
+            &milestone.amount,
+        );
+
+        if was_disputed {
+            if let Some(assigned) = assigned {
+                let mut profile = load_profile(&env, &assigned);
+                profile.open_cases = profile.open_cases.saturating_sub(1);
+                save_profile(&env, &assigned, &profile);
+            }
+        }
+
+        MilestoneReleased {
+            escrow_id,
+            index,
+            approved_by: caller,
+            amount: milestone.amount,
+        }
+        .publish(&env);
+
+        settle_milestone_escrow(&env, escrow, &milestones);
+
+        Ok(())
+    }
+
+    pub fn dispute_milestone(
+        env: Env,
+        caller: Address,
+        escrow_id: u64,
+        index: u32,
+        evidence_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+
+        // Only the parties to the escrow can open a dispute
+        if caller != escrow.customer && caller != escrow.merchant {
+            return Err(Error::Unauthorized);
+        }
+
+        let mut milestones = Self::get_milestones(env.clone(), escrow_id)?;
+        let mut milestone = milestones.get(index).ok_or(Error::MilestoneNotFound)?;
+        if milestone.status != MilestoneStatus::Pending {
+            return Err(Error::InvalidStatus);
+        }
+
+        milestone.status = MilestoneStatus::Disputed;
+        milestone.arbitrator = assign_arbitrator(&env, &escrow);
+        milestones.set(index, milestone);
+        env.storage()
+            .instance()
+            .set(&DataKey::Milestones(escrow_id), &milestones);
+
+        MilestoneDisputed {
+            escrow_id,
+            index,
+            disputed_by: caller,
+            evidence_hash,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn resolve_milestone_dispute(
+        env: Env,
+        arbitrator: Address,
+        escrow_id: u64,
+        index: u32,
+        merchant_share: i128,
+        customer_share: i128,
+    ) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        if !get_arbitrators(&env).contains(&arbitrator) {
+            return Err(Error::NotArbitrator);
+        }
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        let mut milestones = Self::get_milestones(env.clone(), escrow_id)?;
+        let mut milestone = milestones.get(index).ok_or(Error::MilestoneNotFound)?;
+        match milestone.status {
+            MilestoneStatus::Disputed => {}
+            MilestoneStatus::Resolved => return Err(Error::AlreadyResolved),
+            _ => return Err(Error::InvalidStatus),
+        }
+        check_assigned(&env, &escrow, &milestone.arbitrator, &arbitrator)?;
+
+        // The split must account for exactly the milestone amount
+        if merchant_share < 0
+            || customer_share < 0
+            || merchant_share.checked_add(customer_share) != Some(milestone.amount)
+        {
+            return Err(Error::InvalidShares);
+        }
+
+        let milestone_amount = milestone.amount;
+        let assigned = milestone.arbitrator.clone();
+        milestone.status = MilestoneStatus::Resolved;
+        milestone.released_amount = merchant_share;
+        milestone.refunded_amount = customer_share;
+        milestones.set(index, milestone);
+        env.storage()
+            .instance()
+            .set(&DataKey::Milestones(escrow_id), &milestones);
+
+        let fee = arbitrator_fee(&env, milestone_amount);
+        pay_ruling(
+            &env,
+            &escrow,
+            &arbitrator,
+            merchant_share,
+            customer_share,
+            fee,
+        );
+
+        if let Some(assigned) = assigned {
+            let mut profile = load_profile(&env, &assigned);
+            profile.open_cases = profile.open_cases.saturating_sub(1);
+            save_profile(&env, &assigned, &profile);
+        }
+        let mut profile = load_profile(&env, &arbitrator);
+        profile.resolved_count += 1;
+        save_profile(&env, &arbitrator, &profile);
+
+        MilestoneResolved {
+            escrow_id,
+            index,
+            resolved_by: arbitrator,
+            merchant_share,
+            customer_share,
+        }
+        .publish(&env);
+
+        settle_milestone_escrow(&env, escrow, &milestones);
+
+        Ok(())
+    }
+
+    pub fn get_escrow(env: Env, escrow_id: u64) -> Result<Escrow, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Escrow(escrow_id))
+            .ok_or(Error::EscrowNotFound)
+    }
+
+    pub fn get_dispute(env: Env, escrow_id: u64) -> Result<Dispute, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Dispute(escrow_id))
+            .ok_or(Error::DisputeNotFound)
+    }
+
+    pub fn get_dispute_evidence(env: Env, escrow_id: u64) -> Vec<Evidence> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Evidence(escrow_id))
+            .unwrap_or(Vec::new(&env))
+    }
+
+    pub fn set_evidence_window(env: Env, window: u64) -> Result<(), Error> {
+        // Require admin authentication
+        require_admin(&env)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::EvidenceWindow, &window);
+
+        Ok(())
+    }
+
+    pub fn get_evidence_window(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::EvidenceWindow)
+            .unwrap_or(DEFAULT_EVIDENCE_WINDOW)
+    }
+
+    pub fn submit_evidence(
+        env: Env,
+        caller: Address,
+        escrow_id: u64,
+        content_hash: BytesN<32>,
+        uri: String,
+    ) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if caller != escrow.customer && caller != escrow.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if escrow.status != EscrowStatus::Disputed {
+            return Err(Error::InvalidStatus);
+        }
+
+        // Evidence is accepted until the window closes or the party finalizes
+        let dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        let finalized = if caller == escrow.customer {
+            dispute.customer_finalized
+        } else {
+            dispute.merchant_finalized
+        };
+        let now = env.ledger().timestamp();
+        if finalized || now >= dispute.evidence_deadline {
+            return Err(Error::EvidenceWindowClosed);
+        }
+
+        // Each party has its own cap, so one side cannot use up the other's entries
+        let mut evidence = Self::get_dispute_evidence(env.clone(), escrow_id);
+        let submitted = evidence.iter().filter(|e| e.submitted_by == caller).count() as u32;
+        if submitted >= MAX_EVIDENCE_ENTRIES {
+            return Err(Error::EvidenceLimitReached);
+        }
+        evidence.push_back(Evidence {
+            submitted_by: caller.clone(),
+            content_hash: content_hash.clone(),
+            uri: uri.clone(),
+            timestamp: now,
+        });
+        env.storage()
+            .instance()
+            .set(&DataKey::Evidence(escrow_id), &evidence);
+
+        EvidenceSubmitted {
+            escrow_id,
+            submitted_by: caller,
+            content_hash,
+            uri,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn finalize_evidence(env: Env, caller: Address, escrow_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if escrow.status != EscrowStatus::Disputed {
+            return Err(Error::InvalidStatus);
+        }
+
+        let mut dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        if caller == escrow.customer {
+            dispute.customer_finalized = true;
+        } else if caller == escrow.merchant {
+            dispute.merchant_finalized = true;
+        } else {
+            return Err(Error::Unauthorized);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::Dispute(escrow_id), &dispute);
+
+        EvidenceFinalized {
+            escrow_id,
+            finalized_by: caller,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn release_escrow(env: Env, caller: Address, escrow_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+
+        // The customer confirms delivery, or the admin releases on their behalf
+        if caller != escrow.customer && caller != get_admin(&env)? {
+            return Err(Error::Unauthorized);
+        }
+
+        if escrow.milestone_count > 0 {
+            return Err(Error::MilestoneEscrow);
+        }
+
+        match escrow.status {
+            EscrowStatus::Locked => {}
+            EscrowStatus::Resolved => return Err(Error::AlreadyResolved),
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        release_to_merchant(&env, escrow);
+
+        Ok(())
+    }
+
+    pub fn claim_release(env: Env, merchant: Address, escrow_id: u64) -> Result<(), Error> {
+        merchant.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+
+        if merchant != escrow.merchant {
+            return Err(Error::Unauthorized);
+        }
+
+        if escrow.milestone_count > 0 {
+            return Err(Error::MilestoneEscrow);
+        }
+
+        // A dispute opened inside the window moves the escrow out of Locked
+        match escrow.status {
+            EscrowStatus::Locked => {}
+            EscrowStatus::Resolved => return Err(Error::AlreadyResolved),
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        if env.ledger().timestamp() < escrow.release_after {
+            return Err(Error::ReleaseNotDue);
+        }
+
+        release_to_merchant(&env, escrow);
+
+        Ok(())
+    }
+
+    pub fn propose_cancel(
+        env: Env,
+        caller: Address,
+        escrow_id: u64,
+        merchant_share: i128,
+        customer_share: i128,
+    ) -> Result<(), Error> {
+        caller.require_auth();
+
+        let escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if caller != escrow.customer && caller != escrow.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if escrow.milestone_count > 0 {
+            return Err(Error::MilestoneEscrow);
+        }
+
+        // Parties can unwind before a ruling, even with a dispute open
+        match escrow.status {
+            EscrowStatus::Locked | EscrowStatus::Disputed => {}
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        if merchant_share < 0
+            || customer_share < 0
+            || merchant_share.checked_add(customer_share) != Some(escrow.amount)
+        {
+            return Err(Error::InvalidShares);
+        }
+
+        // A new proposal from either side replaces the previous one
+        let proposed_at = env.ledger().timestamp();
+        let proposal = CancelProposal {
+            proposed_by: caller.clone(),
+            merchant_share,
+            customer_share,
+            proposed_at,
+            expires_at: proposed_at.saturating_add(CANCEL_PROPOSAL_TTL),
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::CancelProposal(escrow_id), &proposal);
+
+        CancelProposed {
+            escrow_id,
+            proposed_by: caller,
+            merchant_share,
+            customer_share,
+            expires_at: proposal.expires_at,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn get_cancel_proposal(env: Env, escrow_id: u64) -> Result<CancelProposal, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::CancelProposal(escrow_id))
+            .ok_or(Error::ProposalNotFound)
+    }
+
+    pub fn accept_cancel(env: Env, caller: Address, escrow_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        let proposal = Self::get_cancel_proposal(env.clone(), escrow_id)?;
+
+        // The other party has to accept
+        let counterparty = if proposal.proposed_by == escrow.customer {
+            &escrow.merchant
+        } else {
+            &escrow.customer
+        };
+        if &caller != counterparty {
+            return Err(Error::Unauthorized);
+        }
+
+        match escrow.status {
+            EscrowStatus::Locked | EscrowStatus::Disputed => {}
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        if env.ledger().timestamp() >= proposal.expires_at {
+            return Err(Error::ProposalExpired);
+        }
+
+        // Free the assigned arbitrator from a dispute that no longer needs a ruling
+        if escrow.status == EscrowStatus::Disputed {
+            if let Some(assigned) = Self::get_dispute(env.clone(), escrow_id)?.arbitrator {
+                let mut profile = load_profile(&env, &assigned);
+                profile.open_cases = profile.open_cases.saturating_sub(1);
+                save_profile(&env, &assigned, &profile);
+            }
+        }
+
+        escrow.status = EscrowStatus::Cancelled;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+        env.storage()
+            .instance()
+            .remove(&DataKey::CancelProposal(escrow_id));
+
+        pay_shares(
+            &env,
+            &escrow,
+            proposal.merchant_share,
+            proposal.customer_share,
+        );
+
+        EscrowCancelled {
+            escrow_id,
+            accepted_by: caller,
+            merchant_share: proposal.merchant_share,
+            customer_share: proposal.customer_share,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn dispute_escrow(
+        env: Env,
+        caller: Address,
+        escrow_id: u64,
+        evidence_hash: BytesN<32>,
+    ) -> Result<(), Error> {
+        caller.require_auth();
+
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+
+        // Only the parties to the escrow can open a dispute
+        if caller != escrow.customer && caller != escrow.merchant {
+            return Err(Error::Unauthorized);
+        }
+
+        // Milestone escrows are disputed one milestone at a time
+        if escrow.milestone_count > 0 {
+            return Err(Error::MilestoneEscrow);
+        }
+
+        // Freeze the funds; a resolved escrow cannot be disputed again
+        match escrow.status {
+            EscrowStatus::Locked => {}
+            EscrowStatus::Ruled | EscrowStatus::Appealed | EscrowStatus::Resolved => {
+                return Err(Error::AlreadyResolved)
+            }
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        if env.ledger().timestamp() >= escrow.release_after {
+            return Err(Error::DisputeWindowClosed);
+        }
+
+        escrow.status = EscrowStatus::Disputed;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+
+        let disputed_at = env.ledger().timestamp();
+        let arbitrator = assign_arbitrator(&env, &escrow);
+        let dispute = Dispute {
+            escrow_id,
+            disputed_by: caller.clone(),
+            evidence_hash: evidence_hash.clone(),
+            disputed_at,
+            evidence_deadline: disputed_at.saturating_add(Self::get_evidence_window(env.clone())),
+            customer_finalized: false,
+            merchant_finalized: false,
+            resolved_by: None,
+            merchant_share: 0,
+            customer_share: 0,
+            resolved_at: None,
+            appeal_deadline: 0,
+            arbitrator: arbitrator.clone(),
+            fee: 0,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Dispute(escrow_id), &dispute);
+
+        EscrowDisputed {
+            escrow_id,
+            disputed_by: caller,
+            evidence_hash,
+            disputed_at,
+        }
+        .publish(&env);
+
+        if let Some(arbitrator) = arbitrator {
+            ArbitratorAssigned {
+                escrow_id,
+                arbitrator,
+            }
+            .publish(&env);
+        }
+
+        Ok(())
+    }
+
+    pub fn resolve_dispute(
+        env: Env,
+        arbitrator: Address,
+        escrow_id: u64,
+        merchant_share: i128,
+        customer_share: i128,
+    ) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        if !get_arbitrators(&env).contains(&arbitrator) {
+            return Err(Error::NotArbitrator);
+        }
+
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        match escrow.status {
+            EscrowStatus::Disputed => {}
+            EscrowStatus::Ruled | EscrowStatus::Appealed | EscrowStatus::Resolved => {
+                return Err(Error::AlreadyResolved)
+            }
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        let mut dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        check_assigned(&env, &escrow, &dispute.arbitrator, &arbitrator)?;
+
+        // Both parties get their say before a ruling
+        let both_finalized = dispute.customer_finalized && dispute.merchant_finalized;
+        if !both_finalized && env.ledger().timestamp() < dispute.evidence_deadline {
+            return Err(Error::EvidenceWindowOpen);
+        }
+
+        // The split must account for exactly the escrowed amount
+        if merchant_share < 0
+            || customer_share < 0
+            || merchant_share.checked_add(customer_share) != Some(escrow.amount)
+        {
+            return Err(Error::InvalidShares);
+        }
+
+        // The ruling is only paid out once the appeal window has passed
+        escrow.status = EscrowStatus::Ruled;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+
+        let resolved_at = env.ledger().timestamp();
+        dispute.resolved_by = Some(arbitrator.clone());
+        dispute.merchant_share = merchant_share;
+        dispute.customer_share = customer_share;
+        dispute.resolved_at = Some(resolved_at);
+        dispute.appeal_deadline =
+            resolved_at.saturating_add(Self::get_appeal_config(env.clone()).window);
+        dispute.fee = arbitrator_fee(&env, escrow.amount);
+        env.storage()
+            .instance()
+            .set(&DataKey::Dispute(escrow_id), &dispute);
+
+        if let Some(assigned) = dispute.arbitrator {
+            let mut profile = load_profile(&env, &assigned);
+            profile.open_cases = profile.open_cases.saturating_sub(1);
+            save_profile(&env, &assigned, &profile);
+        }
+        let mut profile = load_profile(&env, &arbitrator);
+        profile.resolved_count += 1;
+        save_profile(&env, &arbitrator, &profile);
+
+        DisputeResolved {
+            escrow_id,
+            resolved_by: arbitrator,
+            merchant_share,
+            customer_share,
+            resolved_at,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn set_appeal_config(env: Env, config: AppealConfig) -> Result<(), Error> {
+        // Require admin authentication
+        require_admin(&env)?;
+
+        // An odd panel always produces a majority between two outcomes
+        if config.panel_size == 0
+            || config.panel_size.is_multiple_of(2)
+            || config.bond_bps as i128 > BPS_DENOMINATOR
+            || config.voting_window == 0
+        {
+            return Err(Error::InvalidConfig);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::AppealConfig, &config);
+
+        Ok(())
+    }
+
+    pub fn get_appeal_config(env: Env) -> AppealConfig {
+        env.storage()
+            .instance()
+            .get(&DataKey::AppealConfig)
+            .unwrap_or(AppealConfig {
+                window: DEFAULT_APPEAL_WINDOW,
+                bond_bps: DEFAULT_APPEAL_BOND_BPS,
+                panel_size: DEFAULT_PANEL_SIZE,
+                voting_window: DEFAULT_VOTING_WINDOW,
+            })
+    }
+
+    pub fn get_appeal(env: Env, escrow_id: u64) -> Result<Appeal, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Appeal(escrow_id))
+            .ok_or(Error::DisputeNotFound)
+    }
+
+    pub fn appeal_ruling(env: Env, caller: Address, escrow_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if escrow.status != EscrowStatus::Ruled {
+            return Err(Error::InvalidStatus);
+        }
+
+        let dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        if env.ledger().timestamp() >= dispute.appeal_deadline {
+            return Err(Error::AppealWindowClosed);
+        }
+
+        // Only a party that received less than the other side may appeal
+        let lost = if caller == escrow.customer {
+            dispute.customer_share <= dispute.merchant_share
+        } else if caller == escrow.merchant {
+            dispute.merchant_share <= dispute.customer_share
+        } else {
+            return Err(Error::Unauthorized);
+        };
+        if !lost {
+            return Err(Error::NotLosingParty);
+        }
+
+        // Seat a panel that excludes the arbitrator who made the ruling and both parties
+        let config = Self::get_appeal_config(env.clone());
+        let original = dispute.resolved_by.clone().unwrap();
+        let mut eligible = Vec::new(&env);
+        for arbitrator in get_arbitrators(&env).iter() {
+            if arbitrator != original
+                && arbitrator != escrow.customer
+                && arbitrator != escrow.merchant
+            {
+                eligible.push_back(arbitrator);
+            }
+        }
+        if eligible.len() < config.panel_size {
+            return Err(Error::NotEnoughArbitrators);
+        }
+        let mut panel = Vec::new(&env);
+        let offset = (escrow_id % eligible.len() as u64) as u32;
+        for i in 0..config.panel_size {
+            panel.push_back(eligible.get((offset + i) % eligible.len()).unwrap());
+        }
+
+        // The bond is a share of the escrowed amount, held until the panel decides
+        let bond = escrow.amount * config.bond_bps as i128 / BPS_DENOMINATOR;
+        if bond > 0 {
+            token::Client::new(&env, &escrow.token).transfer(
+                &caller,
+                env.current_contract_address(),
+                &bond,
+            );
+        }
+
+        escrow.status = EscrowStatus::Appealed;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+
+        let now = env.ledger().timestamp();
+        let appeal = Appeal {
+            escrow_id,
+            appellant: caller.clone(),
+            bond,
+            panel: panel.clone(),
+            votes: Vec::new(&env),
+            filed_at: now,
+            voting_deadline: now.saturating_add(config.voting_window),
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Appeal(escrow_id), &appeal);
+
+        AppealFiled {
+            escrow_id,
+            appellant: caller,
+            bond,
+            panel,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn vote_appeal(
+        env: Env,
+        arbitrator: Address,
+        escrow_id: u64,
+        merchant_share: i128,
+        customer_share: i128,
+    ) -> Result<(), Error> {
+        arbitrator.require_auth();
+
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        if escrow.status != EscrowStatus::Appealed {
+            return Err(Error::InvalidStatus);
+        }
+
+        let mut appeal = Self::get_appeal(env.clone(), escrow_id)?;
+        if env.ledger().timestamp() >= appeal.voting_deadline {
+            return Err(Error::VotingClosed);
+        }
+        if !appeal.panel.contains(&arbitrator) {
+            return Err(Error::NotPanelMember);
+        }
+        if appeal.votes.iter().any(|v| v.arbitrator == arbitrator) {
+            return Err(Error::AlreadyVoted);
+        }
+
+        if merchant_share < 0
+            || customer_share < 0
+            || merchant_share.checked_add(customer_share) != Some(escrow.amount)
+        {
+            return Err(Error::InvalidShares);
+        }
+
+        appeal.votes.push_back(PanelVote {
+            arbitrator: arbitrator.clone(),
+            merchant_share,
+            customer_share,
+        });
+        env.storage()
+            .instance()
+            .set(&DataKey::Appeal(escrow_id), &appeal);
+
+        AppealVoteCast {
+            escrow_id,
+            arbitrator,
+            merchant_share,
+            customer_share,
+        }
+        .publish(&env);
+
+        // A split backed by a majority of the panel is final
+        let matching = appeal
+            .votes
+            .iter()
+            .filter(|v| v.merchant_share == merchant_share)
+            .count() as u32;
+        let majority = matching * 2 > appeal.panel.len();
+        let all_voted = appeal.votes.len() == appeal.panel.len();
+        if !majority && !all_voted {
+            return Ok(());
+        }
+
+        // Without a majority the original ruling stands
+        let mut dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        let (final_merchant, final_customer) = if majority {
+            (merchant_share, customer_share)
+        } else {
+            (dispute.merchant_share, dispute.customer_share)
+        };
+        let overturned = final_merchant != dispute.merchant_share;
+
+        // The bond comes back on a successful appeal and goes to the other party otherwise
+        if appeal.bond > 0 {
+            let bond_recipient = if overturned {
+                appeal.appellant.clone()
+            } else if appeal.appellant == escrow.customer {
+                escrow.merchant.clone()
+            } else {
+                escrow.customer.clone()
+            };
+            token::Client::new(&env, &escrow.token).transfer(
+                &env.current_contract_address(),
+                &bond_recipient,
+                &appeal.bond,
+            );
+        }
+
+        dispute.merchant_share = final_merchant;
+        dispute.customer_share = final_customer;
+        env.storage()
+            .instance()
+            .set(&DataKey::Dispute(escrow_id), &dispute);
+
+        escrow.status = EscrowStatus::Resolved;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+
+        // An overturned ruling earns no fee and counts against the arbitrator
+        let original = dispute.resolved_by.unwrap();
+        let fee = if overturned {
+            let mut profile = load_profile(&env, &original);
+            profile.overturned_count += 1;
+            save_profile(&env, &original, &profile);
+            0
+        } else {
+            dispute.fee
+        };
+        pay_ruling(
+            &env,
+            &escrow,
+            &original,
+            final_merchant,
+            final_customer,
+            fee,
+        );
+
+        AppealDecided {
+            escrow_id,
+            overturned,
+            merchant_share: final_merchant,
+            customer_share: final_customer,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn finalize_ruling(env: Env, escrow_id: u64) -> Result<(), Error> {
+        let mut escrow = Self::get_escrow(env.clone(), escrow_id)?;
+        let dispute = Self::get_dispute(env.clone(), escrow_id)?;
+        let now = env.ledger().timestamp();
+        match escrow.status {
+            // Unappealed rulings become final once the appeal window has passed
+            EscrowStatus::Ruled => {
+                if now < dispute.appeal_deadline {
+                    return Err(Error::AppealWindowOpen);
+                }
+            }
+            // A panel that never reaches a decision leaves the original ruling in place, and
+            // the appellant is not penalised for it
+            EscrowStatus::Appealed => {
+                let appeal = Self::get_appeal(env.clone(), escrow_id)?;
+                if now < appeal.voting_deadline {
+                    return Err(Error::VotingOpen);
+                }
+                if appeal.bond > 0 {
+                    token::Client::new(&env, &escrow.token).transfer(
+                        &env.current_contract_address(),
+                        &appeal.appellant,
+                        &appeal.bond,
+                    );
+                }
+            }
+            EscrowStatus::Resolved => return Err(Error::AlreadyResolved),
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        escrow.status = EscrowStatus::Resolved;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow_id), &escrow);
+
+        pay_ruling(
+            &env,
+            &escrow,
+            &dispute.resolved_by.unwrap(),
+            dispute.merchant_share,
+            dispute.customer_share,
+            dispute.fee,
+        );
+
+        RulingFinalized {
+            escrow_id,
+            merchant_share: dispute.merchant_share,
+            customer_share: dispute.customer_share,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn open_escrow(
+    env: &Env,
+    depositor: &Address,
+    customer: Address,
+    merchant: Address,
+    token: Address,
+    amount: i128,
+    release_after: u64,
+    milestone_count: u32,
+) -> u64 {
+    // Lock the funds in the contract
+    token::Client::new(env, &token).transfer(depositor, env.current_contract_address(), &amount);
+
+    // Get and increment escrow counter
+    let counter: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::EscrowCounter)
+        .unwrap_or(0);
+    let escrow_id = counter + 1;
+
+    let escrow = Escrow {
+        id: escrow_id,
+        customer: customer.clone(),
+        merchant: merchant.clone(),
+        token: token.clone(),
+        amount,
+        status: EscrowStatus::Locked,
+        created_at: env.ledger().timestamp(),
+        release_after,
+        milestone_count,
+    };
+
+    env.storage()
+        .instance()
+        .set(&DataKey::Escrow(escrow_id), &escrow);
+    env.storage()
+        .instance()
+        .set(&DataKey::EscrowCounter, &escrow_id);
+
+    EscrowCreated {
+        escrow_id,
+        customer,
+        merchant,
+        token,
+        amount,
+        release_after,
+    }
+    .publish(env);
+
+    escrow_id
+}
+
+fn pay_shares(env: &Env, escrow: &Escrow, merchant_share: i128, customer_share: i128) {
+    let token_client = token::Client::new(env, &escrow.token);
+    if merchant_share > 0 {
+        pay_merchant_side(env, escrow, merchant_share);
+    }
+    if customer_share > 0 {
+        token_client.transfer(
+            &env.current_contract_address(),
+            &escrow.customer,
+            &customer_share,
+        );
+    }
+}
+
+fn pay_ruling(
+    env: &Env,
+    escrow: &Escrow,
+    arbitrator: &Address,
+    merchant_share: i128,
+    customer_share: i128,
+    fee: i128,
+) {
+    // The fee comes out of each side in proportion to its share
+    let total = merchant_share + customer_share;
+    let merchant_fee = if total > 0 {
+        fee * merchant_share / total
+    } else {
+        0
+    };
+    let customer_fee = fee - merchant_fee;
+    pay_shares(
+        env,
+        escrow,
+        merchant_share - merchant_fee,
+        customer_share - customer_fee,
+    );
+
+    if fee > 0 {
+        token::Client::new(env, &escrow.token).transfer(
+            &env.current_contract_address(),
+            arbitrator,
+            &fee,
+        );
+        ArbitratorFeePaid {
+            escrow_id: escrow.id,
+            arbitrator: arbitrator.clone(),
+            amount: fee,
+        }
+        .publish(env);
+    }
+}
+
+fn pay_merchant_side(env: &Env, escrow: &Escrow, amount: i128) {
+    let token_client = token::Client::new(env, &escrow.token);
+    let beneficiaries: Vec<Beneficiary> = env
+        .storage()
+        .instance()
+        .get(&DataKey::Beneficiaries(escrow.id))
+        .unwrap_or(Vec::new(env));
+    if beneficiaries.is_empty() {
+        token_client.transfer(&env.current_contract_address(), &escrow.merchant, &amount);
+        return;
+    }
+
+    // Each beneficiary takes its basis points; rounding dust goes to the first
+    let mut remaining = amount;
+    for (i, beneficiary) in beneficiaries.iter().enumerate().rev() {
+        let share = if i == 0 {
+            remaining
+        } else {
+            amount * beneficiary.bps as i128 / BPS_DENOMINATOR
+        };
+        remaining -= share;
+        if share > 0 {
+            token_client.transfer(
+                &env.current_contract_address(),
+                &beneficiary.address,
+                &share,
+            );
+            BeneficiaryPaid {
+                escrow_id: escrow.id,
+                beneficiary: beneficiary.address,
+                amount: share,
+            }
+            .publish(env);
+        }
+    }
+}
+
+fn validate_split(beneficiaries: &Vec<Beneficiary>) -> Result<(), Error> {
+    if beneficiaries.is_empty() || beneficiaries.len() > MAX_BENEFICIARIES {
+        return Err(Error::InvalidSplit);
+    }
+
+    let mut total: i128 = 0;
+    for (i, beneficiary) in beneficiaries.iter().enumerate() {
+        if beneficiary.bps == 0 {
+            return Err(Error::InvalidSplit);
+        }
+        // Each address may appear only once
+        if beneficiaries
+            .iter()
+            .take(i)
+            .any(|earlier| earlier.address == beneficiary.address)
+        {
+            return Err(Error::InvalidSplit);
+        }
+        total += beneficiary.bps as i128;
+    }
+    if total != BPS_DENOMINATOR {
+        return Err(Error::InvalidSplit);
+    }
+
+    Ok(())
+}
+
+fn check_assigned(
+    env: &Env,
+    escrow: &Escrow,
+    assigned: &Option<Address>,
+    arbitrator: &Address,
+) -> Result<(), Error> {
+    // Neither party may rule on its own escrow
+    if arbitrator == &escrow.customer || arbitrator == &escrow.merchant {
+        return Err(Error::ConflictOfInterest);
+    }
+
+    // The assigned arbitrator rules, unless they have since left the registry
+    if let Some(assigned) = assigned.clone() {
+        if &assigned != arbitrator && get_arbitrators(env).contains(&assigned) {
+            return Err(Error::NotAssignedArbitrator);
+        }
+    }
+    Ok(())
+}
+
+fn settle_milestone_escrow(env: &Env, mut escrow: Escrow, milestones: &Vec<Milestone>) {
+    // Close the escrow once every milestone has paid out
+    let settled = milestones
+        .iter()
+        .all(|m| m.status == MilestoneStatus::Released || m.status == MilestoneStatus::Resolved);
+    if settled {
+        escrow.status = EscrowStatus::Released;
+        env.storage()
+            .instance()
+            .set(&DataKey::Escrow(escrow.id), &escrow);
+    }
+}
+
+fn release_to_merchant(env: &Env, mut escrow: Escrow) {
+    escrow.status = EscrowStatus::Released;
+    env.storage()
+        .instance()
+        .set(&DataKey::Escrow(escrow.id), &escrow);
+
+    pay_merchant_side(env, &escrow, escrow.amount);
+
+    EscrowReleased {
+        escrow_id: escrow.id,
+        merchant: escrow.merchant,
+        amount: escrow.amount,
+    }
+    .publish(env);
+}
+
+fn get_admin(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)
+}
+
+fn require_admin(env: &Env) -> Result<Address, Error> {
+    let admin = get_admin(env)?;
+    admin.require_auth();
+    Ok(admin)
+}
+
+fn get_arbitrators(env: &Env) -> Vec<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Arbitrators)
+        .unwrap_or(Vec::new(env))
+}
+
+fn load_profile(env: &Env, arbitrator: &Address) -> ArbitratorProfile {
+    env.storage()
+        .instance()
+        .get(&DataKey::Arbitrator(arbitrator.clone()))
+        .unwrap_or(ArbitratorProfile {
+            stake: 0,
+            resolved_count: 0,
+            overturned_count: 0,
+            open_cases: 0,
+        })
+}
+
+fn save_profile(env: &Env, arbitrator: &Address, profile: &ArbitratorProfile) {
+    env.storage()
+        .instance()
+        .set(&DataKey::Arbitrator(arbitrator.clone()), profile);
+}
+
+fn arbitrator_fee(env: &Env, amount: i128) -> i128 {
+    let fee_bps = env
+        .storage()
+        .instance()
+        .get::<_, ArbitratorConfig>(&DataKey::ArbitratorConfig)
+        .map(|config| config.fee_bps)
+        .unwrap_or(0);
+    amount * fee_bps as i128 / BPS_DENOMINATOR
+}
+
+fn assign_arbitrator(env: &Env, escrow: &Escrow) -> Option<Address> {
+    let config = env
+        .storage()
+        .instance()
+        .get::<_, ArbitratorConfig>(&DataKey::ArbitratorConfig);
+
+    // Neither party can be handed their own dispute, and once a registry is configured
+    // only arbitrators holding the minimum stake take new cases
+    let mut eligible = Vec::new(env);
+    for arbitrator in get_arbitrators(env).iter() {
+        if arbitrator == escrow.customer || arbitrator == escrow.merchant {
+            continue;
+        }
+        if let Some(config) = &config {
+            if load_profile(env, &arbitrator).stake < config.min_stake {
+                continue;
+            }
+        }
+        eligible.push_back(arbitrator);
+    }
+    if eligible.is_empty() {
+        return None;
+    }
+
+    let mode = config
+        .map(|config| config.assignment)
+        .unwrap_or(AssignmentMode::RoundRobin);
+    let index = match mode {
+        AssignmentMode::RoundRobin => {
+            let next: u32 = env
+                .storage()
+                .instance()
+                .get(&DataKey::NextArbitrator)
+                .unwrap_or(0);
+            env.storage()
+                .instance()
+                .set(&DataKey::NextArbitrator, &next.wrapping_add(1));
+            next % eligible.len()
+        }
+        AssignmentMode::Random => env.prng().gen_range::<u64>(0..eligible.len() as u64) as u32,
+    };
+
+    let arbitrator = eligible.get(index).unwrap();
+    let mut profile = load_profile(env, &arbitrator);
+    profile.open_cases += 1;
+    save_profile(env, &arbitrator, &profile);
+
+    Some(arbitrator)
 }
 
 mod test;
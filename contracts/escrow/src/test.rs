@@ -1,21 +1,1524 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{vec, Env, String};
+use soroban_sdk::{
+    testutils::Address as _, testutils::Ledger, token, Address, BytesN, Env, String, Vec,
+};
+
+const DISPUTE_WINDOW: u64 = 86_400;
+
+fn create_token(env: &Env, holder: &Address, amount: i128) -> Address {
+    let issuer = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(env, &token).mint(holder, &amount);
+    token
+}
+
+fn close_evidence_window(env: &Env) {
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + DEFAULT_EVIDENCE_WINDOW);
+}
+
+fn close_appeal_window(env: &Env) {
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + DEFAULT_APPEAL_WINDOW);
+}
+
+fn setup(env: &Env) -> (EscrowContractClient<'_>, Address) {
+    let contract_id = env.register(EscrowContract, ());
+    let client = EscrowContractClient::new(env, &contract_id);
+
+    let admin = Address::generate(env);
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    (client, admin)
+}
+
+#[test]
+fn test_initialize_twice_should_fail() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let result = client.try_initialize(&admin);
+    assert_eq!(result, Err(Ok(Error::AlreadyInitialized)));
+}
+
+#[test]
+fn test_create_escrow_locks_funds() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &600, &DISPUTE_WINDOW);
+    assert_eq!(escrow_id, 1);
+
+    let escrow = client.get_escrow(&escrow_id);
+    assert_eq!(escrow.customer, customer);
+    assert_eq!(escrow.merchant, merchant);
+    assert_eq!(escrow.amount, 600);
+    assert_eq!(escrow.status, EscrowStatus::Locked);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&client.address), 600);
+    assert_eq!(token_client.balance(&customer), 400);
+}
+
+#[test]
+fn test_create_escrow_with_zero_amount_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let result = client.try_create_escrow(&customer, &merchant, &token, &0, &DISPUTE_WINDOW);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+#[should_panic]
+fn test_get_nonexistent_escrow_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    client.get_escrow(&999);
+}
+
+#[test]
+fn test_release_escrow_pays_merchant() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.release_escrow(&customer, &escrow_id);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Released);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 1000);
+}
+
+#[test]
+fn test_release_escrow_by_merchant_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    let result = client.try_release_escrow(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_add_and_remove_arbitrator() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let arbitrator = Address::generate(&env);
+
+    client.add_arbitrator(&arbitrator);
+    assert!(client.is_arbitrator(&arbitrator));
+    assert_eq!(client.get_arbitrators().len(), 1);
+
+    client.remove_arbitrator(&arbitrator);
+    assert!(!client.is_arbitrator(&arbitrator));
+
+    let result = client.try_remove_arbitrator(&arbitrator);
+    assert_eq!(result, Err(Ok(Error::NotArbitrator)));
+}
+
+#[test]
+fn test_dispute_escrow_freezes_funds() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let evidence_hash = BytesN::from_array(&env, &[1; 32]);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &evidence_hash);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Disputed);
+    let dispute = client.get_dispute(&escrow_id);
+    assert_eq!(dispute.disputed_by, customer);
+    assert_eq!(dispute.evidence_hash, evidence_hash);
+
+    // Disputed funds can no longer be released
+    let result = client.try_release_escrow(&admin, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_dispute_escrow_by_outsider_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let outsider = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    let result =
+        client.try_dispute_escrow(&outsider, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_resolve_dispute_splits_funds() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&merchant, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    close_evidence_window(&env);
+    client.resolve_dispute(&arbitrator, &escrow_id, &700, &300);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Ruled);
+    let dispute = client.get_dispute(&escrow_id);
+    assert_eq!(dispute.resolved_by, Some(arbitrator));
+    assert_eq!(dispute.merchant_share, 700);
+    assert_eq!(dispute.customer_share, 300);
+
+    close_appeal_window(&env);
+    client.finalize_ruling(&escrow_id);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Resolved);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&merchant), 700);
+    assert_eq!(token_client.balance(&customer), 300);
+}
+
+#[test]
+fn test_resolve_dispute_with_wrong_total_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    close_evidence_window(&env);
+
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &700, &400);
+    assert_eq!(result, Err(Ok(Error::InvalidShares)));
+
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &1100, &-100);
+    assert_eq!(result, Err(Ok(Error::InvalidShares)));
+}
+
+#[test]
+fn test_resolve_dispute_by_non_arbitrator_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let outsider = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    let result = client.try_resolve_dispute(&outsider, &escrow_id, &500, &500);
+    assert_eq!(result, Err(Ok(Error::NotArbitrator)));
+}
+
+#[test]
+fn test_resolve_undisputed_escrow_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &500, &500);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_resolved_escrow_cannot_be_disputed_again() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let evidence_hash = BytesN::from_array(&env, &[1; 32]);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &evidence_hash);
+    close_evidence_window(&env);
+    client.resolve_dispute(&arbitrator, &escrow_id, &0, &1000);
+
+    let result = client.try_dispute_escrow(&merchant, &escrow_id, &evidence_hash);
+    assert_eq!(result, Err(Ok(Error::AlreadyResolved)));
+
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &1000, &0);
+    assert_eq!(result, Err(Ok(Error::AlreadyResolved)));
+}
+
+#[test]
+fn test_create_escrow_sets_release_after() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    env.ledger().set_timestamp(1_000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    let escrow = client.get_escrow(&escrow_id);
+    assert_eq!(escrow.created_at, 1_000);
+    assert_eq!(escrow.release_after, 1_000 + DISPUTE_WINDOW);
+}
+
+#[test]
+fn test_claim_release_after_window() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW);
+    client.claim_release(&merchant, &escrow_id);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Released);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 1000);
+}
+
+#[test]
+fn test_claim_release_before_window_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW - 1);
+    let result = client.try_claim_release(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::ReleaseNotDue)));
+}
+
+#[test]
+fn test_claim_release_by_customer_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW);
+    let result = client.try_claim_release(&customer, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_dispute_before_deadline_blocks_release() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW - 1);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW * 2);
+    let result = client.try_claim_release(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_dispute_after_deadline_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    env.ledger().set_timestamp(DISPUTE_WINDOW);
+    let result =
+        client.try_dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    assert_eq!(result, Err(Ok(Error::DisputeWindowClosed)));
+}
+
+fn milestone_terms(env: &Env, amounts: &[i128]) -> Vec<MilestoneTerms> {
+    let mut terms = Vec::new(env);
+    for (i, amount) in amounts.iter().enumerate() {
+        terms.push_back(MilestoneTerms {
+            amount: *amount,
+            description_hash: BytesN::from_array(env, &[i as u8; 32]),
+        });
+    }
+    terms
+}
+
+#[test]
+fn test_create_milestone_escrow_locks_total() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[200, 300, 500]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    let escrow = client.get_escrow(&escrow_id);
+    assert_eq!(escrow.amount, 1000);
+    assert_eq!(escrow.milestone_count, 3);
+    assert_eq!(client.get_milestones(&escrow_id).len(), 3);
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&client.address),
+        1000
+    );
+}
+
+#[test]
+fn test_create_milestone_escrow_without_milestones_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let result = client.try_create_milestone_escrow(&customer, &merchant, &token, &Vec::new(&env));
+    assert_eq!(result, Err(Ok(Error::InvalidMilestones)));
+
+    let terms = milestone_terms(&env, &[200, 0]);
+    let result = client.try_create_milestone_escrow(&customer, &merchant, &token, &terms);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_approve_milestones_in_order() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[200, 300, 500]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    // The second milestone cannot be released before the first
+    let result = client.try_approve_milestone(&customer, &escrow_id, &1);
+    assert_eq!(result, Err(Ok(Error::MilestoneOutOfOrder)));
+
+    client.approve_milestone(&customer, &escrow_id, &0);
+    client.approve_milestone(&customer, &escrow_id, &1);
+
+    let progress = client.get_milestone_progress(&escrow_id);
+    assert_eq!(progress.released, 500);
+    assert_eq!(progress.outstanding, 500);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 500);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Locked);
+
+    client.approve_milestone(&customer, &escrow_id, &2);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Released);
+}
+
+#[test]
+fn test_arbitrator_can_approve_milestone() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    // Arbitrators only step in once the milestone is disputed
+    let result = client.try_approve_milestone(&arbitrator, &escrow_id, &0);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.dispute_milestone(
+        &customer,
+        &escrow_id,
+        &0,
+        &BytesN::from_array(&env, &[1; 32]),
+    );
+    client.approve_milestone(&arbitrator, &escrow_id, &0);
+
+    let milestone = client.get_milestones(&escrow_id).get(0).unwrap();
+    assert_eq!(milestone.status, MilestoneStatus::Released);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 400);
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).open_cases, 0);
+
+    let result = client.try_approve_milestone(&merchant, &escrow_id, &1);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_unassigned_arbitrator_cannot_approve_milestone() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let arbitrator = staked_arbitrator(&env, &client, &stake_token);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+    client.dispute_milestone(
+        &customer,
+        &escrow_id,
+        &0,
+        &BytesN::from_array(&env, &[1; 32]),
+    );
+
+    // A third party staking in after the dispute was assigned cannot release it
+    let newcomer = staked_arbitrator(&env, &client, &stake_token);
+    let result = client.try_approve_milestone(&newcomer, &escrow_id, &0);
+    assert_eq!(result, Err(Ok(Error::NotAssignedArbitrator)));
+    let result = client.try_approve_milestone(&newcomer, &escrow_id, &1);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    let milestone = client.get_milestones(&escrow_id).get(0).unwrap();
+    assert_eq!(milestone.status, MilestoneStatus::Disputed);
+    assert_eq!(milestone.arbitrator, Some(arbitrator));
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 0);
+}
+
+#[test]
+fn test_unstaked_arbitrator_not_assigned_once_registry_configured() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let appointed = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&appointed);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let staked = staked_arbitrator(&env, &client, &stake_token);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+    for index in 0..2u32 {
+        client.dispute_milestone(
+            &customer,
+            &escrow_id,
+            &index,
+            &BytesN::from_array(&env, &[1; 32]),
+        );
+    }
+
+    // Round robin would reach the appointed arbitrator, but it has no stake
+    for milestone in client.get_milestones(&escrow_id).iter() {
+        assert_eq!(milestone.arbitrator, Some(staked.clone()));
+    }
+    assert_eq!(client.get_arbitrator_profile(&appointed).open_cases, 0);
+}
+
+#[test]
+fn test_dispute_and_resolve_single_milestone() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let evidence_hash = BytesN::from_array(&env, &[9; 32]);
+
+    client.add_arbitrator(&arbitrator);
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    client.approve_milestone(&customer, &escrow_id, &0);
+    client.dispute_milestone(&customer, &escrow_id, &1, &evidence_hash);
+
+    let result = client.try_resolve_milestone_dispute(&arbitrator, &escrow_id, &1, &400, &400);
+    assert_eq!(result, Err(Ok(Error::InvalidShares)));
+
+    client.resolve_milestone_dispute(&arbitrator, &escrow_id, &1, &200, &400);
+
+    let progress = client.get_milestone_progress(&escrow_id);
+    assert_eq!(progress.released, 600);
+    assert_eq!(progress.refunded, 400);
+    assert_eq!(progress.outstanding, 0);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Released);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&merchant), 600);
+    assert_eq!(token_client.balance(&customer), 400);
+}
+
+#[test]
+fn test_disputed_milestone_blocks_later_milestones() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    client.dispute_milestone(
+        &merchant,
+        &escrow_id,
+        &0,
+        &BytesN::from_array(&env, &[1; 32]),
+    );
+
+    let result = client.try_approve_milestone(&customer, &escrow_id, &1);
+    assert_eq!(result, Err(Ok(Error::MilestoneOutOfOrder)));
+
+    let result = client.try_approve_milestone(&customer, &escrow_id, &0);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_milestone_escrow_rejects_whole_escrow_operations() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    let result = client.try_release_escrow(&customer, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::MilestoneEscrow)));
+
+    let result =
+        client.try_dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    assert_eq!(result, Err(Ok(Error::MilestoneEscrow)));
+
+    let result = client.try_approve_milestone(&customer, &escrow_id, &5);
+    assert_eq!(result, Err(Ok(Error::MilestoneNotFound)));
+}
+
+#[test]
+fn test_parties_submit_evidence() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let uri = String::from_str(&env, "ipfs://bafy-delivery-photos");
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    env.ledger().set_timestamp(100);
+    client.submit_evidence(
+        &customer,
+        &escrow_id,
+        &BytesN::from_array(&env, &[2; 32]),
+        &uri,
+    );
+    client.submit_evidence(
+        &merchant,
+        &escrow_id,
+        &BytesN::from_array(&env, &[3; 32]),
+        &String::from_str(&env, "https://example.com/tracking"),
+    );
+
+    let evidence = client.get_dispute_evidence(&escrow_id);
+    assert_eq!(evidence.len(), 2);
+
+    let first = evidence.get(0).unwrap();
+    assert_eq!(first.submitted_by, customer);
+    assert_eq!(first.content_hash, BytesN::from_array(&env, &[2; 32]));
+    assert_eq!(first.uri, uri);
+    assert_eq!(first.timestamp, 100);
+    assert_eq!(evidence.get(1).unwrap().submitted_by, merchant);
+}
+
+#[test]
+fn test_submit_evidence_on_undisputed_escrow_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    let result = client.try_submit_evidence(
+        &customer,
+        &escrow_id,
+        &BytesN::from_array(&env, &[2; 32]),
+        &String::from_str(&env, ""),
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_submit_evidence_after_window_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    close_evidence_window(&env);
+
+    let result = client.try_submit_evidence(
+        &merchant,
+        &escrow_id,
+        &BytesN::from_array(&env, &[2; 32]),
+        &String::from_str(&env, ""),
+    );
+    assert_eq!(result, Err(Ok(Error::EvidenceWindowClosed)));
+}
+
+#[test]
+fn test_submit_evidence_is_capped() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let uri = String::from_str(&env, "ipfs://bafy");
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    for i in 0..MAX_EVIDENCE_ENTRIES {
+        let hash = BytesN::from_array(&env, &[i as u8; 32]);
+        client.submit_evidence(&customer, &escrow_id, &hash, &uri);
+    }
+
+    let result = client.try_submit_evidence(
+        &customer,
+        &escrow_id,
+        &BytesN::from_array(&env, &[99; 32]),
+        &uri,
+    );
+    assert_eq!(result, Err(Ok(Error::EvidenceLimitReached)));
+}
+
+#[test]
+fn test_evidence_cap_is_per_party() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let uri = String::from_str(&env, "ipfs://bafy");
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    for i in 0..MAX_EVIDENCE_ENTRIES {
+        let hash = BytesN::from_array(&env, &[i as u8; 32]);
+        client.submit_evidence(&customer, &escrow_id, &hash, &uri);
+    }
+
+    // The customer filling its cap does not stop the merchant from responding
+    client.submit_evidence(
+        &merchant,
+        &escrow_id,
+        &BytesN::from_array(&env, &[99; 32]),
+        &uri,
+    );
+    let evidence = client.get_dispute_evidence(&escrow_id);
+    assert_eq!(evidence.len(), MAX_EVIDENCE_ENTRIES + 1);
+    assert_eq!(evidence.last().unwrap().submitted_by, merchant);
+}
+
+#[test]
+fn test_finalized_party_cannot_submit_more_evidence() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let uri = String::from_str(&env, "ipfs://bafy");
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    client.finalize_evidence(&customer, &escrow_id);
+
+    let result = client.try_submit_evidence(
+        &customer,
+        &escrow_id,
+        &BytesN::from_array(&env, &[2; 32]),
+        &uri,
+    );
+    assert_eq!(result, Err(Ok(Error::EvidenceWindowClosed)));
+
+    // The other party can still submit
+    client.submit_evidence(
+        &merchant,
+        &escrow_id,
+        &BytesN::from_array(&env, &[3; 32]),
+        &uri,
+    );
+    assert_eq!(client.get_dispute_evidence(&escrow_id).len(), 1);
+}
+
+#[test]
+fn test_resolve_dispute_while_evidence_window_open_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    client.finalize_evidence(&customer, &escrow_id);
+
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &500, &500);
+    assert_eq!(result, Err(Ok(Error::EvidenceWindowOpen)));
+}
+
+#[test]
+fn test_resolve_dispute_after_both_parties_finalize() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    client.finalize_evidence(&customer, &escrow_id);
+    client.finalize_evidence(&merchant, &escrow_id);
+
+    client.resolve_dispute(&arbitrator, &escrow_id, &500, &500);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Ruled);
+}
+
+#[test]
+fn test_set_evidence_window() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    assert_eq!(client.get_evidence_window(), DEFAULT_EVIDENCE_WINDOW);
+    client.set_evidence_window(&3_600);
+
+    env.ledger().set_timestamp(1_000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    assert_eq!(client.get_dispute(&escrow_id).evidence_deadline, 4_600);
+}
+
+// Opens a dispute ruled 800/200 in the merchant's favour, with a spare panel of three
+fn ruled_escrow(
+    env: &Env,
+    client: &EscrowContractClient,
+) -> (u64, Address, Address, Address, Vec<Address>) {
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+    let arbitrator = Address::generate(env);
+    let token = create_token(env, &customer, 1100);
+    token::StellarAssetClient::new(env, &token).mint(&merchant, &100);
+
+    client.add_arbitrator(&arbitrator);
+    let mut panel = Vec::new(env);
+    for _ in 0..DEFAULT_PANEL_SIZE {
+        let member = Address::generate(env);
+        client.add_arbitrator(&member);
+        panel.push_back(member);
+    }
+
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(env, &[1; 32]));
+    close_evidence_window(env);
+    client.resolve_dispute(&arbitrator, &escrow_id, &800, &200);
+
+    (escrow_id, customer, merchant, token, panel)
+}
+
+#[test]
+fn test_finalize_ruling_before_appeal_window_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, _customer, _merchant, _token, _panel) = ruled_escrow(&env, &client);
+
+    let result = client.try_finalize_ruling(&escrow_id);
+    assert_eq!(result, Err(Ok(Error::AppealWindowOpen)));
+}
+
+#[test]
+fn test_appeal_by_winning_party_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, _customer, merchant, _token, _panel) = ruled_escrow(&env, &client);
+
+    let result = client.try_appeal_ruling(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::NotLosingParty)));
+}
+
+#[test]
+fn test_appeal_after_window_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, _merchant, _token, _panel) = ruled_escrow(&env, &client);
+
+    close_appeal_window(&env);
+    let result = client.try_appeal_ruling(&customer, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::AppealWindowClosed)));
+}
+
+#[test]
+fn test_appeal_without_enough_arbitrators_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, _merchant, _token, panel) = ruled_escrow(&env, &client);
+
+    client.remove_arbitrator(&panel.get(0).unwrap());
+
+    let result = client.try_appeal_ruling(&customer, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::NotEnoughArbitrators)));
+}
+
+#[test]
+fn test_appeal_overturned_by_panel_majority() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, merchant, token, panel) = ruled_escrow(&env, &client);
+
+    client.appeal_ruling(&customer, &escrow_id);
+
+    let appeal = client.get_appeal(&escrow_id);
+    assert_eq!(appeal.appellant, customer);
+    assert_eq!(appeal.bond, 100);
+    assert_eq!(appeal.panel.len(), DEFAULT_PANEL_SIZE);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Appealed);
+
+    client.vote_appeal(&panel.get(0).unwrap(), &escrow_id, &200, &800);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Appealed);
+    client.vote_appeal(&panel.get(1).unwrap(), &escrow_id, &200, &800);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Resolved);
+    let dispute = client.get_dispute(&escrow_id);
+    assert_eq!(dispute.merchant_share, 200);
+    assert_eq!(dispute.customer_share, 800);
+
+    // The appellant won, so the bond comes back
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&customer), 100 + 800);
+    assert_eq!(token_client.balance(&merchant), 100 + 200);
+
+    let original = dispute.resolved_by.unwrap();
+    let profile = client.get_arbitrator_profile(&original);
+    assert_eq!(profile.resolved_count, 1);
+    assert_eq!(profile.overturned_count, 1);
+}
+
+#[test]
+fn test_appeal_upheld_forfeits_bond() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, merchant, token, panel) = ruled_escrow(&env, &client);
+
+    client.appeal_ruling(&customer, &escrow_id);
+    client.vote_appeal(&panel.get(0).unwrap(), &escrow_id, &800, &200);
+    client.vote_appeal(&panel.get(1).unwrap(), &escrow_id, &500, &500);
+    client.vote_appeal(&panel.get(2).unwrap(), &escrow_id, &800, &200);
+
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Resolved);
+
+    // The ruling stood, so the bond goes to the merchant
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&customer), 200);
+    assert_eq!(token_client.balance(&merchant), 100 + 800 + 100);
+}
+
+#[test]
+fn test_appeal_vote_rules() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, _merchant, _token, panel) = ruled_escrow(&env, &client);
+    let outsider = Address::generate(&env);
+
+    client.appeal_ruling(&customer, &escrow_id);
+
+    let result = client.try_vote_appeal(&outsider, &escrow_id, &200, &800);
+    assert_eq!(result, Err(Ok(Error::NotPanelMember)));
+
+    let member = panel.get(0).unwrap();
+    let result = client.try_vote_appeal(&member, &escrow_id, &200, &900);
+    assert_eq!(result, Err(Ok(Error::InvalidShares)));
+
+    client.vote_appeal(&member, &escrow_id, &200, &800);
+    let result = client.try_vote_appeal(&member, &escrow_id, &200, &800);
+    assert_eq!(result, Err(Ok(Error::AlreadyVoted)));
+}
+
+#[test]
+fn test_appeal_panel_excludes_parties() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, merchant, _token, panel) = ruled_escrow(&env, &client);
+
+    // Both parties registered as arbitrators can never sit on their own appeal
+    client.add_arbitrator(&customer);
+    client.add_arbitrator(&merchant);
+    client.appeal_ruling(&customer, &escrow_id);
+
+    let seated = client.get_appeal(&escrow_id).panel;
+    assert!(!seated.contains(&customer));
+    assert!(!seated.contains(&merchant));
+    for member in panel.iter() {
+        assert!(seated.contains(&member));
+    }
+}
+
+#[test]
+fn test_stalled_appeal_keeps_original_ruling() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let (escrow_id, customer, merchant, token, panel) = ruled_escrow(&env, &client);
+
+    client.appeal_ruling(&customer, &escrow_id);
+    client.vote_appeal(&panel.get(0).unwrap(), &escrow_id, &200, &800);
+
+    let result = client.try_finalize_ruling(&escrow_id);
+    assert_eq!(result, Err(Ok(Error::VotingOpen)));
+
+    let deadline = client.get_appeal(&escrow_id).voting_deadline;
+    env.ledger().set_timestamp(deadline);
+    let result = client.try_vote_appeal(&panel.get(1).unwrap(), &escrow_id, &200, &800);
+    assert_eq!(result, Err(Ok(Error::VotingClosed)));
+
+    client.finalize_ruling(&escrow_id);
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Resolved);
+
+    // The 800/200 ruling is paid out and the bond goes back to the appellant
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&customer), 100 + 200);
+    assert_eq!(token_client.balance(&merchant), 100 + 800);
+}
+
+#[test]
+fn test_set_appeal_config() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let config = AppealConfig {
+        window: 3_600,
+        bond_bps: 500,
+        panel_size: 5,
+        voting_window: 86_400,
+    };
+    client.set_appeal_config(&config);
+    assert_eq!(client.get_appeal_config(), config);
+
+    let even_panel = AppealConfig {
+        window: 3_600,
+        bond_bps: 500,
+        panel_size: 4,
+        voting_window: 86_400,
+    };
+    let result = client.try_set_appeal_config(&even_panel);
+    assert_eq!(result, Err(Ok(Error::InvalidConfig)));
+}
+
+fn registry_config(
+    env: &Env,
+    client: &EscrowContractClient,
+    assignment: AssignmentMode,
+) -> Address {
+    let stake_token = create_token(env, &Address::generate(env), 0);
+    client.set_arbitrator_config(&ArbitratorConfig {
+        stake_token: stake_token.clone(),
+        min_stake: 500,
+        fee_bps: 200,
+        assignment,
+    });
+    stake_token
+}
+
+fn staked_arbitrator(env: &Env, client: &EscrowContractClient, stake_token: &Address) -> Address {
+    let arbitrator = Address::generate(env);
+    token::StellarAssetClient::new(env, stake_token).mint(&arbitrator, &1000);
+    client.register_arbitrator(&arbitrator, &500);
+    arbitrator
+}
+
+#[test]
+fn test_register_arbitrator_with_stake() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let arbitrator = Address::generate(&env);
+
+    let result = client.try_register_arbitrator(&arbitrator, &500);
+    assert_eq!(result, Err(Ok(Error::RegistryNotConfigured)));
+
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    token::StellarAssetClient::new(&env, &stake_token).mint(&arbitrator, &1000);
+
+    let result = client.try_register_arbitrator(&arbitrator, &499);
+    assert_eq!(result, Err(Ok(Error::StakeTooLow)));
+
+    client.register_arbitrator(&arbitrator, &600);
+    assert!(client.is_arbitrator(&arbitrator));
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).stake, 600);
+    assert_eq!(
+        token::Client::new(&env, &stake_token).balance(&arbitrator),
+        400
+    );
+
+    let result = client.try_register_arbitrator(&arbitrator, &500);
+    assert_eq!(result, Err(Ok(Error::AlreadyRegistered)));
+
+    client.unregister_arbitrator(&arbitrator);
+    assert!(!client.is_arbitrator(&arbitrator));
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).stake, 0);
+    assert_eq!(
+        token::Client::new(&env, &stake_token).balance(&arbitrator),
+        1000
+    );
+}
+
+#[test]
+fn test_disputes_assigned_round_robin() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let first = staked_arbitrator(&env, &client, &stake_token);
+    let second = staked_arbitrator(&env, &client, &stake_token);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 2000);
+    let evidence_hash = BytesN::from_array(&env, &[1; 32]);
+
+    let first_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    let second_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &first_id, &evidence_hash);
+    client.dispute_escrow(&customer, &second_id, &evidence_hash);
+
+    assert_eq!(
+        client.get_dispute(&first_id).arbitrator,
+        Some(first.clone())
+    );
+    assert_eq!(
+        client.get_dispute(&second_id).arbitrator,
+        Some(second.clone())
+    );
+    assert_eq!(client.get_arbitrator_profile(&first).open_cases, 1);
+
+    // An arbitrator with an open case cannot pull their stake
+    let result = client.try_unregister_arbitrator(&first);
+    assert_eq!(result, Err(Ok(Error::ArbitratorBusy)));
+
+    close_evidence_window(&env);
+    let result = client.try_resolve_dispute(&second, &first_id, &500, &500);
+    assert_eq!(result, Err(Ok(Error::NotAssignedArbitrator)));
+
+    client.resolve_dispute(&first, &first_id, &500, &500);
+    let profile = client.get_arbitrator_profile(&first);
+    assert_eq!(profile.open_cases, 0);
+    assert_eq!(profile.resolved_count, 1);
+}
+
+#[test]
+fn test_random_assignment_picks_registered_arbitrator() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::Random);
+    let mut arbitrators = Vec::new(&env);
+    for _ in 0..3 {
+        arbitrators.push_back(staked_arbitrator(&env, &client, &stake_token));
+    }
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+
+    let assigned = client.get_dispute(&escrow_id).arbitrator.unwrap();
+    assert!(arbitrators.contains(&assigned));
+}
+
+#[test]
+fn test_milestone_dispute_assigned_arbitrator_rules() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let first = staked_arbitrator(&env, &client, &stake_token);
+    let second = staked_arbitrator(&env, &client, &stake_token);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+    client.dispute_milestone(
+        &customer,
+        &escrow_id,
+        &0,
+        &BytesN::from_array(&env, &[1; 32]),
+    );
+
+    let milestone = client.get_milestones(&escrow_id).get(0).unwrap();
+    assert_eq!(milestone.arbitrator, Some(first.clone()));
+    assert_eq!(client.get_arbitrator_profile(&first).open_cases, 1);
+
+    let result = client.try_resolve_milestone_dispute(&second, &escrow_id, &0, &200, &200);
+    assert_eq!(result, Err(Ok(Error::NotAssignedArbitrator)));
+
+    client.resolve_milestone_dispute(&first, &escrow_id, &0, &200, &200);
+    let profile = client.get_arbitrator_profile(&first);
+    assert_eq!(profile.open_cases, 0);
+    assert_eq!(profile.resolved_count, 1);
+}
+
+#[test]
+fn test_party_registered_as_arbitrator_cannot_rule_on_own_milestone() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    // The merchant stakes its way into the registry
+    token::StellarAssetClient::new(&env, &stake_token).mint(&merchant, &1000);
+    client.register_arbitrator(&merchant, &500);
+
+    let terms = milestone_terms(&env, &[400, 600]);
+    let escrow_id = client.create_milestone_escrow(&customer, &merchant, &token, &terms);
+
+    let result = client.try_approve_milestone(&merchant, &escrow_id, &0);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.dispute_milestone(
+        &customer,
+        &escrow_id,
+        &0,
+        &BytesN::from_array(&env, &[1; 32]),
+    );
+
+    // The merchant is the only registered arbitrator, so nobody is assigned
+    let milestone = client.get_milestones(&escrow_id).get(0).unwrap();
+    assert_eq!(milestone.arbitrator, None);
+
+    let result = client.try_approve_milestone(&merchant, &escrow_id, &0);
+    assert_eq!(result, Err(Ok(Error::ConflictOfInterest)));
+
+    let result = client.try_resolve_milestone_dispute(&merchant, &escrow_id, &0, &400, &0);
+    assert_eq!(result, Err(Ok(Error::ConflictOfInterest)));
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 0);
+}
 
 #[test]
-fn test() {
+fn test_arbitrator_fee_paid_from_shares() {
     let env = Env::default();
-    let contract_id = env.register(Contract, ());
-    let client = ContractClient::new(&env, &contract_id);
+    let (client, _admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let arbitrator = staked_arbitrator(&env, &client, &stake_token);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    close_evidence_window(&env);
+    client.resolve_dispute(&arbitrator, &escrow_id, &750, &250);
+    assert_eq!(client.get_dispute(&escrow_id).fee, 20);
+
+    close_appeal_window(&env);
+    client.finalize_ruling(&escrow_id);
+
+    // A 2% fee, taken 15/5 in line with the 750/250 split
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&arbitrator), 20);
+    assert_eq!(token_client.balance(&merchant), 735);
+    assert_eq!(token_client.balance(&customer), 245);
+}
+
+#[test]
+fn test_slash_arbitrator_below_minimum_removes_them() {
+    let env = Env::default();
+    let (client, admin) = setup(&env);
+    let stake_token = registry_config(&env, &client, AssignmentMode::RoundRobin);
+    let arbitrator = staked_arbitrator(&env, &client, &stake_token);
+
+    let result = client.try_slash_arbitrator(&arbitrator, &501);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+
+    client.slash_arbitrator(&arbitrator, &100);
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).stake, 400);
+    assert!(!client.is_arbitrator(&arbitrator));
+    assert_eq!(token::Client::new(&env, &stake_token).balance(&admin), 100);
+
+    // Topping the stake back up restores the arbitrator
+    client.register_arbitrator(&arbitrator, &100);
+    assert!(client.is_arbitrator(&arbitrator));
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).stake, 500);
+}
+
+fn split(env: &Env, shares: &[(&Address, u32)]) -> Vec<Beneficiary> {
+    let mut beneficiaries = Vec::new(env);
+    for (address, bps) in shares {
+        beneficiaries.push_back(Beneficiary {
+            address: (*address).clone(),
+            bps: *bps,
+        });
+    }
+    beneficiaries
+}
+
+#[test]
+fn test_split_escrow_release_pays_each_beneficiary() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let platform = Address::generate(&env);
+    let shipping = Address::generate(&env);
+    let token = create_token(&env, &customer, 1001);
+
+    let beneficiaries = split(
+        &env,
+        &[(&seller, 8_500), (&platform, 1_000), (&shipping, 500)],
+    );
+    let escrow_id =
+        client.create_split_escrow(&customer, &token, &1001, &DISPUTE_WINDOW, &beneficiaries);
+    assert_eq!(client.get_escrow(&escrow_id).merchant, seller);
+    assert_eq!(client.get_beneficiaries(&escrow_id), beneficiaries);
+
+    client.release_escrow(&customer, &escrow_id);
+
+    // Rounding dust stays with the first beneficiary
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&seller), 851);
+    assert_eq!(token_client.balance(&platform), 100);
+    assert_eq!(token_client.balance(&shipping), 50);
+}
+
+#[test]
+fn test_split_escrow_rejects_invalid_splits() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let platform = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    let invalid = [
+        split(&env, &[]),
+        split(&env, &[(&seller, 9_000), (&platform, 500)]),
+        split(&env, &[(&seller, 10_000), (&platform, 0)]),
+        split(&env, &[(&seller, 5_000), (&seller, 5_000)]),
+    ];
+    for beneficiaries in invalid.iter() {
+        let result = client.try_create_split_escrow(
+            &customer,
+            &token,
+            &1000,
+            &DISPUTE_WINDOW,
+            beneficiaries,
+        );
+        assert_eq!(result, Err(Ok(Error::InvalidSplit)));
+    }
+}
+
+#[test]
+fn test_dispute_sets_new_split() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let shipping = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    client.add_arbitrator(&arbitrator);
+
+    let escrow_id = client.create_split_escrow(
+        &customer,
+        &token,
+        &1000,
+        &DISPUTE_WINDOW,
+        &split(&env, &[(&seller, 8_000), (&shipping, 2_000)]),
+    );
+
+    let new_split = split(&env, &[(&seller, 5_000), (&shipping, 5_000)]);
+    let result = client.try_update_split(&arbitrator, &escrow_id, &new_split);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    client.update_split(&arbitrator, &escrow_id, &new_split);
+    assert_eq!(client.get_beneficiaries(&escrow_id), new_split);
+
+    // The merchant side of the ruling follows the new split
+    close_evidence_window(&env);
+    client.resolve_dispute(&arbitrator, &escrow_id, &600, &400);
+    close_appeal_window(&env);
+    client.finalize_ruling(&escrow_id);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&seller), 300);
+    assert_eq!(token_client.balance(&shipping), 300);
+    assert_eq!(token_client.balance(&customer), 400);
+}
+
+#[test]
+fn test_mutual_cancel_refunds_customer() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    client.propose_cancel(&merchant, &escrow_id, &0, &1000);
+    let proposal = client.get_cancel_proposal(&escrow_id);
+    assert_eq!(proposal.proposed_by, merchant);
+    assert_eq!(
+        proposal.expires_at,
+        proposal.proposed_at + CANCEL_PROPOSAL_TTL
+    );
+
+    // The proposer cannot accept their own proposal
+    let result = client.try_accept_cancel(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.accept_cancel(&customer, &escrow_id);
 
-    let words = client.hello(&String::from_str(&env, "Dev"));
     assert_eq!(
-        words,
-        vec![
-            &env,
-            String::from_str(&env, "Hello"),
-            String::from_str(&env, "Dev"),
-        ]
+        client.get_escrow(&escrow_id).status,
+        EscrowStatus::Cancelled
     );
+    assert_eq!(token::Client::new(&env, &token).balance(&customer), 1000);
+    let result = client.try_get_cancel_proposal(&escrow_id);
+    assert_eq!(result, Err(Ok(Error::ProposalNotFound)));
+}
+
+#[test]
+fn test_mutual_cancel_with_split_settles_dispute() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let arbitrator = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    client.add_arbitrator(&arbitrator);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+    client.dispute_escrow(&customer, &escrow_id, &BytesN::from_array(&env, &[1; 32]));
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).open_cases, 1);
+
+    let result = client.try_propose_cancel(&customer, &escrow_id, &300, &800);
+    assert_eq!(result, Err(Ok(Error::InvalidShares)));
+
+    client.propose_cancel(&customer, &escrow_id, &300, &700);
+    client.accept_cancel(&merchant, &escrow_id);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&merchant), 300);
+    assert_eq!(token_client.balance(&customer), 700);
+    assert_eq!(client.get_arbitrator_profile(&arbitrator).open_cases, 0);
+
+    close_evidence_window(&env);
+    let result = client.try_resolve_dispute(&arbitrator, &escrow_id, &500, &500);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_expired_cancel_proposal_should_fail() {
+    let env = Env::default();
+    let (client, _admin) = setup(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let escrow_id = client.create_escrow(&customer, &merchant, &token, &1000, &DISPUTE_WINDOW);
+
+    client.propose_cancel(&customer, &escrow_id, &0, &1000);
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + CANCEL_PROPOSAL_TTL);
+
+    let result = client.try_accept_cancel(&merchant, &escrow_id);
+    assert_eq!(result, Err(Ok(Error::ProposalExpired)));
+    assert_eq!(client.get_escrow(&escrow_id).status, EscrowStatus::Locked);
 }
@@ -1,11 +1,102 @@
 #![no_std]
-use soroban_sdk::{contract, contracterror, contractevent, contractimpl, contracttype, Address, Env};
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractclient, contracterror, contractevent, contractimpl, contracttype,
+    panic_with_error, symbol_short, token, vec,
+    xdr::ToXdr,
+    Address, BytesN, Env, IntoVal, String, Symbol, Vec,
+};
+
+// The subset of the escrow contract used to hold payments during the dispute window
+#[contractclient(name = "EscrowClient")]
+pub trait EscrowInterface {
+    fn create_escrow_for(
+        env: Env,
+        depositor: Address,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        dispute_window: u64,
+    ) -> u64;
+}
+
+// A DEX router that converts completed amounts into the merchant's settlement token
+#[contractclient(name = "SwapRouterClient")]
+pub trait SwapRouterInterface {
+    fn swap_exact_in(
+        env: Env,
+        sender: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: i128,
+        min_out: i128,
+        to: Address,
+    ) -> i128;
+}
+
+// SEP-40 price feed types, as published by oracles such as Reflector
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum Asset {
+    Stellar(Address),
+    Other(Symbol),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PriceData {
+    pub price: i128,
+    pub timestamp: u64,
+}
+
+// A SEP-40 oracle quoting token prices in a single fiat currency
+#[contractclient(name = "PriceOracleClient")]
+pub trait PriceOracleInterface {
+    fn decimals(env: Env) -> u32;
+
+    fn lastprice(env: Env, asset: Asset) -> Option<PriceData>;
+
+    fn prices(env: Env, asset: Asset, records: u32) -> Option<Vec<PriceData>>;
+}
+
+const MAX_SPLIT_PARTS: u32 = 10;
+const MAX_BATCH_SIZE: u32 = 50;
+const DEFAULT_SETTLEMENT_PERIOD: u64 = 24 * 60 * 60;
+const MANDATE_PERIOD: u64 = 30 * 24 * 60 * 60;
+const BPS_DENOMINATOR: i128 = 10_000;
+// Target ledger close time, used to turn an intent's expiry into a TTL
+const LEDGER_SECONDS: u64 = 5;
+// Fiat amounts are given in minor units, e.g. cents
+const FIAT_DECIMALS: u32 = 2;
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
     Payment(u64),
     PaymentCounter,
+    Admin,
+    EscrowContract,
+    MerchantDisputeWindow(Address),
+    Subscription(u64),
+    SubscriptionCounter,
+    Invoice(u64),
+    InvoiceCounter,
+    SplitPayment(u64),
+    SplitPaymentCounter,
+    MerchantBalance(Address, Address),
+    SettlementConfig,
+    SwapRouter,
+    MerchantSettlement(Address),
+    PaymentSettlement(u64),
+    PriceOracle(Symbol),
+    MerchantSigningKey(Address),
+    IntentNonce(Address, u64),
+    Mandate(u64),
+    MandateCounter,
+    Stream(u64),
+    StreamCounter,
+    FeeConfig,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -15,6 +106,51 @@ pub enum PaymentStatus {
     Completed,
     Refunded,
     Cancelled,
+    InEscrow,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum SubscriptionStatus {
+    Active,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum MandateStatus {
+    Active,
+    Revoked,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum StreamStatus {
+    Active,
+    Completed,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+    Expired,
+    Voided,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[contracttype]
+pub enum SplitPaymentStatus {
+    Pending,
+    PartiallySettled,
+    Completed,
+    Refunded,
+    Cancelled,
+    Settled,
 }
 
 #[contracterror]
@@ -24,6 +160,33 @@ pub enum Error {
     InvalidStatus = 2,
     AlreadyProcessed = 3,
     Unauthorized = 4,
+    NotInitialized = 5,
+    AlreadyInitialized = 6,
+    EscrowNotConfigured = 7,
+    SubscriptionNotFound = 8,
+    InvalidAmount = 9,
+    ChargeNotDue = 10,
+    InvoiceNotFound = 11,
+    InvoiceExpired = 12,
+    SplitPaymentNotFound = 13,
+    BatchTooLarge = 14,
+    InsufficientBalance = 15,
+    InvalidConfig = 16,
+    SwapRouterNotConfigured = 17,
+    SlippageExceeded = 18,
+    OracleNotConfigured = 19,
+    PriceUnavailable = 20,
+    StalePrice = 21,
+    PriceDeviationExceeded = 22,
+    SigningKeyNotSet = 23,
+    IntentExpired = 24,
+    NonceUsed = 25,
+    InvalidIntent = 26,
+    MandateNotFound = 27,
+    MandateExpired = 28,
+    CapExceeded = 29,
+    StreamNotFound = 30,
+    EscrowFailed = 31,
 }
 
 #[contractevent]
@@ -32,6 +195,16 @@ pub struct PaymentCompleted {
     pub payment_id: u64,
     pub merchant: Address,
     pub amount: i128,
+    pub tip: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlatformFeeCharged {
+    pub payment_id: u64,
+    pub recipient: Address,
+    pub token: Address,
+    pub amount: i128,
 }
 
 #[contractevent]
@@ -50,169 +223,2295 @@ pub struct PaymentCancelled {
     pub timestamp: u64,
 }
 
-#[derive(Clone)]
-#[contracttype]
-pub struct Payment {
-    pub id: u64,
-    pub customer: Address,
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentEscrowed {
+    pub payment_id: u64,
+    pub escrow_id: u64,
     pub merchant: Address,
     pub amount: i128,
-    pub token: Address,
-    pub status: PaymentStatus,
-    pub created_at: u64,
+    pub dispute_window: u64,
 }
 
-#[contract]
-pub struct PaymentContract;
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionCreated {
+    pub subscription_id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval: u64,
+    pub max_periods: u32,
+}
 
-#[contractimpl]
-impl PaymentContract {
-    pub fn create_payment(
-        env: Env,
-        customer: Address,
-        merchant: Address,
-        amount: i128,
-        token: Address,
-    ) -> u64 {
-        customer.require_auth();
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionCharged {
+    pub subscription_id: u64,
+    pub payment_id: u64,
+    pub period: u32,
+    pub amount: i128,
+}
 
-        let counter: u64 = env
-            .storage()
-            .instance()
-            .get(&DataKey::PaymentCounter)
-            .unwrap_or(0);
-        let payment_id = counter + 1;
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionStatusChanged {
+    pub subscription_id: u64,
+    pub status: SubscriptionStatus,
+    pub changed_by: Address,
+}
 
-        let payment = Payment {
-            id: payment_id,
-            customer: customer.clone(),
-            merchant,
-            amount,
-            token,
-            status: PaymentStatus::Pending,
-            created_at: env.ledger().timestamp(),
-        };
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MandateCreated {
+    pub mandate_id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub per_payment_cap: i128,
+    pub period_cap: i128,
+    pub expiry: u64,
+}
 
-        env.storage()
-            .instance()
-            .set(&DataKey::Payment(payment_id), &payment);
-        env.storage()
-            .instance()
-            .set(&DataKey::PaymentCounter, &payment_id);
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MandateCharged {
+    pub mandate_id: u64,
+    pub payment_id: u64,
+    pub amount: i128,
+    pub spent_in_period: i128,
+}
 
-        payment_id
-    }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MandateRevoked {
+    pub mandate_id: u64,
+    pub revoked_by: Address,
+}
 
-    pub fn get_payment(env: &Env, payment_id: u64) -> Payment {
-        env.storage()
-            .instance()
-            .get(&DataKey::Payment(payment_id))
-            .expect("Payment not found")
-    }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamCreated {
+    pub stream_id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub deposit: i128,
+    pub start: u64,
+    pub end: u64,
+}
 
-    pub fn complete_payment(env: Env, admin: Address, payment_id: u64) -> Result<(), Error> {
-        admin.require_auth();
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamWithdrawn {
+    pub stream_id: u64,
+    pub merchant: Address,
+    pub amount: i128,
+}
 
-        // Check if payment exists
-        if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
-            return Err(Error::PaymentNotFound);
-        }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamCancelled {
+    pub stream_id: u64,
+    pub cancelled_by: Address,
+    pub merchant_amount: i128,
+    pub customer_amount: i128,
+}
 
-        let mut payment = PaymentContract::get_payment(&env, payment_id);
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvoiceCreated {
+    pub invoice_id: u64,
+    pub merchant: Address,
+    pub amount: i128,
+    pub due_date: u64,
+    pub order_ref: String,
+}
 
-        match payment.status {
-            PaymentStatus::Pending => {
-                payment.status = PaymentStatus::Completed;
-            }
-            PaymentStatus::Completed => return Err(Error::AlreadyProcessed),
-            PaymentStatus::Refunded => return Err(Error::InvalidStatus),
-            PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
-        }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvoicePaid {
+    pub invoice_id: u64,
+    pub payment_id: u64,
+    pub payer: Address,
+}
 
-        env.storage()
-            .instance()
-            .set(&DataKey::Payment(payment_id), &payment);
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvoiceClosed {
+    pub invoice_id: u64,
+    pub status: InvoiceStatus,
+}
 
-        PaymentCompleted {
-            payment_id,
-            merchant: payment.merchant,
-            amount: payment.amount,
-        };
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvoiceReopened {
+    pub invoice_id: u64,
+    pub payment_id: u64,
+}
 
-        Ok(())
-    }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SplitPaymentCreated {
+    pub split_id: u64,
+    pub customer: Address,
+    pub total: i128,
+    pub payment_ids: Vec<u64>,
+}
 
-    pub fn refund_payment(env: Env, admin: Address, payment_id: u64) -> Result<(), Error> {
-        admin.require_auth();
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantCredited {
+    pub merchant: Address,
+    pub token: Address,
+    pub payment_id: u64,
+    pub amount: i128,
+}
 
-        // Check if payment exists
-        if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
-            return Err(Error::PaymentNotFound);
-        }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantWithdrawal {
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub destination: Address,
+}
 
-        let mut payment = PaymentContract::get_payment(&env, payment_id);
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentSwapped {
+    pub payment_id: u64,
+    pub token_in: Address,
+    pub amount_in: i128,
+    pub token_out: Address,
+    pub amount_out: i128,
+}
 
-        match payment.status {
-            PaymentStatus::Pending => {
-                payment.status = PaymentStatus::Refunded;
-            }
-            PaymentStatus::Completed => return Err(Error::InvalidStatus),
-            PaymentStatus::Refunded => return Err(Error::AlreadyProcessed),
-            PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
-        }
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentPriced {
+    pub payment_id: u64,
+    pub currency: Symbol,
+    pub fiat_amount: i128,
+    pub amount: i128,
+    pub price: i128,
+}
 
-        env.storage()
-            .instance()
-            .set(&DataKey::Payment(payment_id), &payment);
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PaymentIntentUsed {
+    pub payment_id: u64,
+    pub merchant: Address,
+    pub nonce: u64,
+    pub order_ref: String,
+}
 
-        PaymentRefunded {
-            payment_id,
-            customer: payment.customer,
-            amount: payment.amount,
-        };
+#[derive(Clone)]
+#[contracttype]
+pub struct Payment {
+    pub id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub token: Address,
+    pub status: PaymentStatus,
+    pub created_at: u64,
+    pub dispute_window: Option<u64>,
+    pub escrow_id: Option<u64>,
+    pub subscription_id: Option<u64>,
+    pub invoice_id: Option<u64>,
+    pub split_id: Option<u64>,
+    pub mandate_id: Option<u64>,
+    pub quote: PriceQuote,
+    pub tip_amount: i128,
+    pub tip_recipient: Option<Address>,
+    pub fee: i128,
+    pub order_ref: Option<String>,
+}
 
-        Ok(())
-    }
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct Settlement {
+    pub token: Address,
+    pub amount: i128,
+}
 
-    pub fn cancel_payment(env: Env, caller: Address, payment_id: u64) -> Result<(), Error> {
-        caller.require_auth();
+// Terms a merchant signs off-chain; the signature covers the XDR encoding of this struct
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PaymentIntent {
+    pub contract: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub order_ref: String,
+    pub nonce: u64,
+    pub expires_at: u64,
+}
 
-        // Check if payment exists
-        if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
-            return Err(Error::PaymentNotFound);
-        }
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct FiatQuote {
+    pub currency: Symbol,
+    pub fiat_amount: i128,
+    pub price: i128,
+    pub decimals: u32,
+    pub priced_at: u64,
+}
 
-        let mut payment = PaymentContract::get_payment(&env, payment_id);
+// Contract types cannot hold an Option of another contract struct, so a payment that was
+// not priced in fiat carries `None`
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum PriceQuote {
+    None,
+    Fiat(FiatQuote),
+}
 
-        // Check authorization: caller must be customer, merchant, or admin
-        let is_authorized = payment.customer == caller || payment.merchant == caller;
-        if !is_authorized {
-            return Err(Error::Unauthorized);
-        }
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct OracleConfig {
+    pub oracle: Address,
+    pub max_age: u64,
+    pub max_deviation_bps: u32,
+}
 
-        // Check payment status is Pending
-        match payment.status {
-            PaymentStatus::Pending => {
-                payment.status = PaymentStatus::Cancelled;
-            }
-            PaymentStatus::Completed => return Err(Error::InvalidStatus),
-            PaymentStatus::Refunded => return Err(Error::InvalidStatus),
-            PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
-        }
+// Swaps are bounded by oracle prices in `price_currency`, not by the router's own quote
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct SwapRouterConfig {
+    pub router: Address,
+    pub price_currency: Symbol,
+}
 
-        env.storage()
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct SettlementPreference {
+    pub token: Address,
+    pub max_slippage_bps: u32,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct SplitPayment {
+    pub id: u64,
+    pub customer: Address,
+    pub token: Address,
+    pub total: i128,
+    pub payment_ids: Vec<u64>,
+    pub created_at: u64,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Invoice {
+    pub id: u64,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub due_date: u64,
+    pub order_ref: String,
+    pub payer: Option<Address>,
+    pub status: InvoiceStatus,
+    pub payment_id: Option<u64>,
+    pub created_at: u64,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct Subscription {
+    pub id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub interval: u64,
+    pub max_periods: u32,
+    pub periods_charged: u32,
+    pub next_charge_at: u64,
+    pub status: SubscriptionStatus,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct Mandate {
+    pub id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub per_payment_cap: i128,
+    pub period_cap: i128,
+    pub expiry: u64,
+    pub period_start: u64,
+    pub spent_in_period: i128,
+    pub status: MandateStatus,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct Stream {
+    pub id: u64,
+    pub customer: Address,
+    pub merchant: Address,
+    pub token: Address,
+    pub deposit: i128,
+    pub start: u64,
+    pub end: u64,
+    pub withdrawn: i128,
+    pub status: StreamStatus,
+    pub cancelled_at: Option<u64>,
+    pub created_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct FeeConfig {
+    pub recipient: Address,
+    pub fee_bps: u32,
+    pub exempt_tips: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct SettlementConfig {
+    pub period: u64,
+    pub reserve_bps: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct MerchantBalance {
+    pub available: i128,
+    pub pending: i128,
+    pub pending_until: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct BalanceSummary {
+    pub available: i128,
+    pub pending: i128,
+    pub reserved: i128,
+    pub withdrawable: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct BatchResult {
+    pub id: u64,
+    pub success: bool,
+    pub error_code: u32,
+}
+
+#[contract]
+pub struct PaymentContract;
+
+#[contractimpl]
+impl PaymentContract {
+    pub fn initialize(env: Env, admin: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&DataKey::Admin) {
+            return Err(Error::AlreadyInitialized);
+        }
+
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        Ok(())
+    }
+
+    pub fn set_escrow_contract(env: Env, escrow_contract: Address) -> Result<(), Error> {
+        require_admin(&env)?;
+
+        env.storage()
+            .instance()
+            .set(&DataKey::EscrowContract, &escrow_contract);
+
+        Ok(())
+    }
+
+    pub fn set_settlement_config(env: Env, config: SettlementConfig) -> Result<(), Error> {
+        require_admin(&env)?;
+
+        if config.period == 0 || config.reserve_bps as i128 > BPS_DENOMINATOR {
+            return Err(Error::InvalidConfig);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::SettlementConfig, &config);
+
+        Ok(())
+    }
+
+    pub fn get_settlement_config(env: Env) -> SettlementConfig {
+        env.storage()
+            .instance()
+            .get(&DataKey::SettlementConfig)
+            .unwrap_or(SettlementConfig {
+                period: DEFAULT_SETTLEMENT_PERIOD,
+                reserve_bps: 0,
+            })
+    }
+
+    pub fn set_fee_config(env: Env, config: Option<FeeConfig>) -> Result<(), Error> {
+        require_admin(&env)?;
+
+        // None turns the platform fee off
+        match config {
+            Some(config) => {
+                if config.fee_bps as i128 > BPS_DENOMINATOR {
+                    return Err(Error::InvalidConfig);
+                }
+                env.storage().instance().set(&DataKey::FeeConfig, &config);
+            }
+            None => env.storage().instance().remove(&DataKey::FeeConfig),
+        }
+
+        Ok(())
+    }
+
+    pub fn get_fee_config(env: Env) -> Option<FeeConfig> {
+        env.storage().instance().get(&DataKey::FeeConfig)
+    }
+
+    pub fn get_merchant_balance(env: Env, merchant: Address, token: Address) -> BalanceSummary {
+        let balance = load_balance(&env, &merchant, &token);
+        let config = Self::get_settlement_config(env);
+
+        // A flat share of the whole balance is held back for refunds; it does not follow
+        // which completed payments can still be refunded
+        let reserved =
+            (balance.available + balance.pending) * config.reserve_bps as i128 / BPS_DENOMINATOR;
+        BalanceSummary {
+            available: balance.available,
+            pending: balance.pending,
+            reserved,
+            withdrawable: (balance.available - reserved).max(0),
+        }
+    }
+
+    pub fn withdraw(
+        env: Env,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        destination: Address,
+    ) -> Result<(), Error> {
+        merchant.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        let summary = Self::get_merchant_balance(env.clone(), merchant.clone(), token.clone());
+        if amount > summary.withdrawable {
+            return Err(Error::InsufficientBalance);
+        }
+
+        let mut balance = load_balance(&env, &merchant, &token);
+        balance.available -= amount;
+        save_balance(&env, &merchant, &token, &balance);
+
+        token::Client::new(&env, &token).transfer(
+            &env.current_contract_address(),
+            &destination,
+            &amount,
+        );
+
+        MerchantWithdrawal {
+            merchant,
+            token,
+            amount,
+            destination,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn set_swap_router(env: Env, config: SwapRouterConfig) -> Result<(), Error> {
+        require_admin(&env)?;
+
+        env.storage().instance().set(&DataKey::SwapRouter, &config);
+
+        Ok(())
+    }
+
+    pub fn get_swap_router(env: Env) -> Option<SwapRouterConfig> {
+        env.storage().instance().get(&DataKey::SwapRouter)
+    }
+
+    pub fn set_settlement_token(
+        env: Env,
+        merchant: Address,
+        preference: Option<SettlementPreference>,
+    ) -> Result<(), Error> {
+        merchant.require_auth();
+
+        // None goes back to settling in whatever token the customer paid with
+        let key = DataKey::MerchantSettlement(merchant);
+        match preference {
+            Some(preference) => {
+                if preference.max_slippage_bps as i128 > BPS_DENOMINATOR {
+                    return Err(Error::InvalidConfig);
+                }
+                env.storage().instance().set(&key, &preference);
+            }
+            None => env.storage().instance().remove(&key),
+        }
+
+        Ok(())
+    }
+
+    pub fn get_settlement_token(env: Env, merchant: Address) -> Option<SettlementPreference> {
+        env.storage()
+            .instance()
+            .get(&DataKey::MerchantSettlement(merchant))
+    }
+
+    pub fn set_price_oracle(
+        env: Env,
+        currency: Symbol,
+        config: Option<OracleConfig>,
+    ) -> Result<(), Error> {
+        require_admin(&env)?;
+
+        let key = DataKey::PriceOracle(currency);
+        match config {
+            Some(config) => {
+                if config.max_age == 0 || config.max_deviation_bps as i128 > BPS_DENOMINATOR {
+                    return Err(Error::InvalidConfig);
+                }
+                env.storage().instance().set(&key, &config);
+            }
+            None => env.storage().instance().remove(&key),
+        }
+
+        Ok(())
+    }
+
+    pub fn get_price_oracle(env: Env, currency: Symbol) -> Option<OracleConfig> {
+        env.storage()
+            .instance()
+            .get(&DataKey::PriceOracle(currency))
+    }
+
+    pub fn get_escrow_contract(env: Env) -> Option<Address> {
+        env.storage().instance().get(&DataKey::EscrowContract)
+    }
+
+    pub fn set_dispute_window(env: Env, merchant: Address, dispute_window: Option<u64>) {
+        merchant.require_auth();
+
+        // A window opts the merchant into escrow-backed payments; None opts out
+        let key = DataKey::MerchantDisputeWindow(merchant);
+        match dispute_window {
+            Some(window) => env.storage().instance().set(&key, &window),
+            None => env.storage().instance().remove(&key),
+        }
+    }
+
+    pub fn get_dispute_window(env: Env, merchant: Address) -> Option<u64> {
+        env.storage()
+            .instance()
+            .get(&DataKey::MerchantDisputeWindow(merchant))
+    }
+
+    pub fn set_signing_key(env: Env, merchant: Address, public_key: Option<BytesN<32>>) {
+        merchant.require_auth();
+
+        // The ed25519 key the merchant signs payment intents with; None disables intents
+        let key = DataKey::MerchantSigningKey(merchant);
+        match public_key {
+            Some(public_key) => env.storage().instance().set(&key, &public_key),
+            None => env.storage().instance().remove(&key),
+        }
+    }
+
+    pub fn get_signing_key(env: Env, merchant: Address) -> Option<BytesN<32>> {
+        env.storage()
+            .instance()
+            .get(&DataKey::MerchantSigningKey(merchant))
+    }
+
+    pub fn create_payment(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        amount: i128,
+        token: Address,
+    ) -> u64 {
+        customer.require_auth();
+
+        let payment = place_payment(&env, customer, merchant, amount, token);
+        save_payment(&env, &payment);
+
+        payment.id
+    }
+
+    pub fn create_payment_with_tip(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        amount: i128,
+        token: Address,
+        tip_amount: i128,
+        tip_recipient: Option<Address>,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        if tip_amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // The tip is held here until completion, then paid to the merchant or tip recipient
+        token::Client::new(&env, &token).transfer(
+            &customer,
+            env.current_contract_address(),
+            &tip_amount,
+        );
+
+        let mut payment = place_payment(&env, customer, merchant, amount, token);
+        payment.tip_amount = tip_amount;
+        payment.tip_recipient = tip_recipient;
+        save_payment(&env, &payment);
+
+        Ok(payment.id)
+    }
+
+    pub fn create_payment_from_intent(
+        env: Env,
+        customer: Address,
+        intent: PaymentIntent,
+        signature: BytesN<64>,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        if intent.contract != env.current_contract_address() || intent.amount <= 0 {
+            return Err(Error::InvalidIntent);
+        }
+        if env.ledger().timestamp() > intent.expires_at {
+            return Err(Error::IntentExpired);
+        }
+        let nonce_key = DataKey::IntentNonce(intent.merchant.clone(), intent.nonce);
+        if env.storage().persistent().has(&nonce_key) {
+            return Err(Error::NonceUsed);
+        }
+        let public_key = Self::get_signing_key(env.clone(), intent.merchant.clone())
+            .ok_or(Error::SigningKeyNotSet)?;
+
+        // Traps on a bad signature, so a tampered amount or recipient never gets through
+        let payload = intent.clone().to_xdr(&env);
+        env.crypto()
+            .ed25519_verify(&public_key, &payload, &signature);
+
+        // Used nonces get their own entry, kept live until the intent expires; after that
+        // the expiry check rejects a replay, and an archived entry still counts as used
+        let storage = env.storage().persistent();
+        storage.set(&nonce_key, &true);
+        let ledgers = (intent.expires_at - env.ledger().timestamp()).div_ceil(LEDGER_SECONDS);
+        let ttl = ledgers.min(env.storage().max_ttl() as u64) as u32;
+        storage.extend_ttl(&nonce_key, ttl, ttl);
+
+        let mut payment = place_payment(
+            &env,
+            customer,
+            intent.merchant.clone(),
+            intent.amount,
+            intent.token,
+        );
+        payment.order_ref = Some(intent.order_ref.clone());
+        save_payment(&env, &payment);
+
+        PaymentIntentUsed {
+            payment_id: payment.id,
+            merchant: intent.merchant,
+            nonce: intent.nonce,
+            order_ref: intent.order_ref,
+        }
+        .publish(&env);
+
+        Ok(payment.id)
+    }
+
+    pub fn create_fiat_payment(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        fiat_amount: i128,
+        currency: Symbol,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        if fiat_amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // The fiat amount is in minor units and converted at the oracle rate
+        let quote = quote_fiat(&env, &token, fiat_amount, currency)?;
+        let amount = fiat_to_token(&env, &token, &quote)?;
+
+        let mut payment = place_payment(&env, customer, merchant, amount, token);
+        payment.quote = PriceQuote::Fiat(quote.clone());
+        save_payment(&env, &payment);
+
+        PaymentPriced {
+            payment_id: payment.id,
+            currency: quote.currency,
+            fiat_amount,
+            amount,
+            price: quote.price,
+        }
+        .publish(&env);
+
+        Ok(payment.id)
+    }
+
+    pub fn create_invoice(
+        env: Env,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        due_date: u64,
+        order_ref: String,
+        payer: Option<Address>,
+    ) -> Result<u64, Error> {
+        merchant.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        if due_date <= env.ledger().timestamp() {
+            return Err(Error::InvoiceExpired);
+        }
+
+        let counter: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::InvoiceCounter)
+            .unwrap_or(0);
+        let invoice_id = counter + 1;
+
+        let invoice = Invoice {
+            id: invoice_id,
+            merchant: merchant.clone(),
+            token,
+            amount,
+            due_date,
+            order_ref: order_ref.clone(),
+            payer,
+            status: InvoiceStatus::Open,
+            payment_id: None,
+            created_at: env.ledger().timestamp(),
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Invoice(invoice_id), &invoice);
+        env.storage()
+            .instance()
+            .set(&DataKey::InvoiceCounter, &invoice_id);
+
+        InvoiceCreated {
+            invoice_id,
+            merchant,
+            amount,
+            due_date,
+            order_ref,
+        }
+        .publish(&env);
+
+        Ok(invoice_id)
+    }
+
+    pub fn get_invoice(env: Env, invoice_id: u64) -> Result<Invoice, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Invoice(invoice_id))
+            .ok_or(Error::InvoiceNotFound)
+    }
+
+    pub fn pay_invoice(env: Env, customer: Address, invoice_id: u64) -> Result<u64, Error> {
+        customer.require_auth();
+
+        let mut invoice = Self::get_invoice(env.clone(), invoice_id)?;
+        if invoice.status != InvoiceStatus::Open {
+            return Err(Error::InvalidStatus);
+        }
+        if env.ledger().timestamp() > invoice.due_date {
+            return Err(Error::InvoiceExpired);
+        }
+
+        // A restricted invoice can only be paid by the named payer
+        if let Some(payer) = &invoice.payer {
+            if payer != &customer {
+                return Err(Error::Unauthorized);
+            }
+        }
+
+        let mut payment = place_payment(
+            &env,
+            customer.clone(),
+            invoice.merchant.clone(),
+            invoice.amount,
+            invoice.token.clone(),
+        );
+        payment.invoice_id = Some(invoice_id);
+        save_payment(&env, &payment);
+
+        invoice.status = InvoiceStatus::Paid;
+        invoice.payment_id = Some(payment.id);
+        env.storage()
+            .instance()
+            .set(&DataKey::Invoice(invoice_id), &invoice);
+
+        InvoicePaid {
+            invoice_id,
+            payment_id: payment.id,
+            payer: customer,
+        }
+        .publish(&env);
+
+        Ok(payment.id)
+    }
+
+    pub fn void_invoice(env: Env, merchant: Address, invoice_id: u64) -> Result<(), Error> {
+        merchant.require_auth();
+
+        let mut invoice = Self::get_invoice(env.clone(), invoice_id)?;
+        if merchant != invoice.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if invoice.status != InvoiceStatus::Open {
+            return Err(Error::InvalidStatus);
+        }
+
+        invoice.status = InvoiceStatus::Voided;
+        close_invoice(&env, &invoice);
+
+        Ok(())
+    }
+
+    pub fn expire_invoice(env: Env, invoice_id: u64) -> Result<(), Error> {
+        // Anyone can mark an overdue invoice as expired
+        let mut invoice = Self::get_invoice(env.clone(), invoice_id)?;
+        if invoice.status != InvoiceStatus::Open {
+            return Err(Error::InvalidStatus);
+        }
+        if env.ledger().timestamp() <= invoice.due_date {
+            return Err(Error::InvalidStatus);
+        }
+
+        invoice.status = InvoiceStatus::Expired;
+        close_invoice(&env, &invoice);
+
+        Ok(())
+    }
+
+    pub fn create_subscription(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+        interval: u64,
+        max_periods: u32,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        if amount <= 0 || interval == 0 || max_periods == 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // One allowance covers every period, so later charges need no customer signature.
+        // It is added to any allowance already granted for other subscriptions or mandates
+        let client = token::Client::new(&env, &token);
+        let contract = env.current_contract_address();
+        let allowance = amount
+            .checked_mul(max_periods as i128)
+            .and_then(|total| total.checked_add(client.allowance(&customer, &contract)))
+            .ok_or(Error::InvalidAmount)?;
+        client.approve(
+            &customer,
+            &contract,
+            &allowance,
+            &env.ledger().max_live_until_ledger(),
+        );
+
+        let counter: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::SubscriptionCounter)
+            .unwrap_or(0);
+        let subscription_id = counter + 1;
+
+        let now = env.ledger().timestamp();
+        let subscription = Subscription {
+            id: subscription_id,
+            customer: customer.clone(),
+            merchant: merchant.clone(),
+            token,
+            amount,
+            interval,
+            max_periods,
+            periods_charged: 0,
+            next_charge_at: now,
+            status: SubscriptionStatus::Active,
+            created_at: now,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Subscription(subscription_id), &subscription);
+        env.storage()
+            .instance()
+            .set(&DataKey::SubscriptionCounter, &subscription_id);
+
+        SubscriptionCreated {
+            subscription_id,
+            customer,
+            merchant,
+            amount,
+            interval,
+            max_periods,
+        }
+        .publish(&env);
+
+        Ok(subscription_id)
+    }
+
+    pub fn get_subscription(env: Env, subscription_id: u64) -> Result<Subscription, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Subscription(subscription_id))
+            .ok_or(Error::SubscriptionNotFound)
+    }
+
+    pub fn charge_subscription(env: Env, subscription_id: u64) -> Result<u64, Error> {
+        // Anyone may trigger a due charge; the terms fix who pays whom and how much
+        let mut subscription = Self::get_subscription(env.clone(), subscription_id)?;
+        if subscription.status != SubscriptionStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+        if env.ledger().timestamp() < subscription.next_charge_at {
+            return Err(Error::ChargeNotDue);
+        }
+
+        let contract = env.current_contract_address();
+        token::Client::new(&env, &subscription.token).transfer_from(
+            &contract,
+            &subscription.customer,
+            &contract,
+            &subscription.amount,
+        );
+
+        let mut payment = new_payment(
+            &env,
+            subscription.customer.clone(),
+            subscription.merchant.clone(),
+            subscription.amount,
+            subscription.token.clone(),
+        );
+        payment.status = PaymentStatus::Completed;
+        payment.subscription_id = Some(subscription_id);
+        credit_merchant(&env, &mut payment)?;
+        save_payment(&env, &payment);
+
+        subscription.periods_charged += 1;
+        subscription.next_charge_at = subscription
+            .next_charge_at
+            .saturating_add(subscription.interval);
+        if subscription.periods_charged == subscription.max_periods {
+            subscription.status = SubscriptionStatus::Completed;
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::Subscription(subscription_id), &subscription);
+
+        SubscriptionCharged {
+            subscription_id,
+            payment_id: payment.id,
+            period: subscription.periods_charged,
+            amount: subscription.amount,
+        }
+        .publish(&env);
+
+        Ok(payment.id)
+    }
+
+    pub fn pause_subscription(
+        env: Env,
+        caller: Address,
+        subscription_id: u64,
+    ) -> Result<(), Error> {
+        let mut subscription = load_subscription_for(&env, &caller, subscription_id)?;
+        if subscription.status != SubscriptionStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+
+        subscription.status = SubscriptionStatus::Paused;
+        save_subscription(&env, &subscription, caller);
+
+        Ok(())
+    }
+
+    pub fn resume_subscription(
+        env: Env,
+        caller: Address,
+        subscription_id: u64,
+    ) -> Result<(), Error> {
+        let mut subscription = load_subscription_for(&env, &caller, subscription_id)?;
+        if subscription.status != SubscriptionStatus::Paused {
+            return Err(Error::InvalidStatus);
+        }
+
+        // Periods that fell inside the pause are skipped rather than charged on resume
+        let now = env.ledger().timestamp();
+        if subscription.next_charge_at < now {
+            subscription.next_charge_at = now;
+        }
+        subscription.status = SubscriptionStatus::Active;
+        save_subscription(&env, &subscription, caller);
+
+        Ok(())
+    }
+
+    pub fn cancel_subscription(
+        env: Env,
+        caller: Address,
+        subscription_id: u64,
+    ) -> Result<(), Error> {
+        let mut subscription = load_subscription_for(&env, &caller, subscription_id)?;
+        match subscription.status {
+            SubscriptionStatus::Active | SubscriptionStatus::Paused => {}
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        // Give back the allowance for periods that will never be charged. Only the customer
+        // can sign for that; after a merchant cancels, the customer revokes it themselves
+        if caller == subscription.customer {
+            let unused = subscription
+                .amount
+                .saturating_mul((subscription.max_periods - subscription.periods_charged) as i128);
+            let client = token::Client::new(&env, &subscription.token);
+            let contract = env.current_contract_address();
+            let remaining = client
+                .allowance(&caller, &contract)
+                .saturating_sub(unused)
+                .max(0);
+            client.approve(
+                &caller,
+                &contract,
+                &remaining,
+                &env.ledger().max_live_until_ledger(),
+            );
+        }
+
+        subscription.status = SubscriptionStatus::Cancelled;
+        save_subscription(&env, &subscription, caller);
+
+        Ok(())
+    }
+
+    pub fn create_mandate(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        per_payment_cap: i128,
+        period_cap: i128,
+        expiry: u64,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        let now = env.ledger().timestamp();
+        if per_payment_cap <= 0 || period_cap < per_payment_cap || expiry <= now {
+            return Err(Error::InvalidAmount);
+        }
+
+        // The allowance covers the period cap for every period the mandate can be charged in,
+        // added to any allowance already granted for other subscriptions or mandates
+        let periods = (expiry - now).div_ceil(MANDATE_PERIOD);
+        let client = token::Client::new(&env, &token);
+        let contract = env.current_contract_address();
+        let allowance = period_cap
+            .checked_mul(periods as i128)
+            .and_then(|total| total.checked_add(client.allowance(&customer, &contract)))
+            .ok_or(Error::InvalidAmount)?;
+        client.approve(
+            &customer,
+            &contract,
+            &allowance,
+            &env.ledger().max_live_until_ledger(),
+        );
+
+        let counter: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::MandateCounter)
+            .unwrap_or(0);
+        let mandate_id = counter + 1;
+
+        let mandate = Mandate {
+            id: mandate_id,
+            customer: customer.clone(),
+            merchant: merchant.clone(),
+            token,
+            per_payment_cap,
+            period_cap,
+            expiry,
+            period_start: now,
+            spent_in_period: 0,
+            status: MandateStatus::Active,
+            created_at: now,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Mandate(mandate_id), &mandate);
+        env.storage()
+            .instance()
+            .set(&DataKey::MandateCounter, &mandate_id);
+
+        MandateCreated {
+            mandate_id,
+            customer,
+            merchant,
+            per_payment_cap,
+            period_cap,
+            expiry,
+        }
+        .publish(&env);
+
+        Ok(mandate_id)
+    }
+
+    pub fn get_mandate(env: Env, mandate_id: u64) -> Result<Mandate, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Mandate(mandate_id))
+            .ok_or(Error::MandateNotFound)
+    }
+
+    pub fn charge_mandate(
+        env: Env,
+        merchant: Address,
+        mandate_id: u64,
+        amount: i128,
+    ) -> Result<u64, Error> {
+        merchant.require_auth();
+
+        let mut mandate = Self::get_mandate(env.clone(), mandate_id)?;
+        if merchant != mandate.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if mandate.status != MandateStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+        let now = env.ledger().timestamp();
+        if now >= mandate.expiry {
+            return Err(Error::MandateExpired);
+        }
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Periods run back to back from creation; spending resets when a new one starts
+        let elapsed = (now - mandate.period_start) / MANDATE_PERIOD;
+        if elapsed > 0 {
+            mandate.period_start += elapsed * MANDATE_PERIOD;
+            mandate.spent_in_period = 0;
+        }
+        if amount > mandate.per_payment_cap || mandate.spent_in_period + amount > mandate.period_cap
+        {
+            return Err(Error::CapExceeded);
+        }
+
+        let contract = env.current_contract_address();
+        token::Client::new(&env, &mandate.token).transfer_from(
+            &contract,
+            &mandate.customer,
+            &contract,
+            &amount,
+        );
+
+        let mut payment = new_payment(
+            &env,
+            mandate.customer.clone(),
+            merchant,
+            amount,
+            mandate.token.clone(),
+        );
+        payment.status = PaymentStatus::Completed;
+        payment.mandate_id = Some(mandate_id);
+        credit_merchant(&env, &mut payment)?;
+        save_payment(&env, &payment);
+
+        mandate.spent_in_period += amount;
+        env.storage()
+            .instance()
+            .set(&DataKey::Mandate(mandate_id), &mandate);
+
+        MandateCharged {
+            mandate_id,
+            payment_id: payment.id,
+            amount,
+            spent_in_period: mandate.spent_in_period,
+        }
+        .publish(&env);
+
+        Ok(payment.id)
+    }
+
+    pub fn revoke_mandate(env: Env, caller: Address, mandate_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        // Either side can end the mandate; charges stop immediately
+        let mut mandate = Self::get_mandate(env.clone(), mandate_id)?;
+        if caller != mandate.customer && caller != mandate.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if mandate.status != MandateStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+
+        mandate.status = MandateStatus::Revoked;
+        env.storage()
+            .instance()
+            .set(&DataKey::Mandate(mandate_id), &mandate);
+
+        MandateRevoked {
+            mandate_id,
+            revoked_by: caller,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    pub fn create_stream(
+        env: Env,
+        customer: Address,
+        merchant: Address,
+        token: Address,
+        deposit: i128,
+        start: u64,
+        end: u64,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        let now = env.ledger().timestamp();
+        if deposit <= 0 || start >= end || end <= now {
+            return Err(Error::InvalidAmount);
+        }
+
+        // The deposit vests to the merchant linearly between start and end
+        token::Client::new(&env, &token).transfer(
+            &customer,
+            env.current_contract_address(),
+            &deposit,
+        );
+
+        let counter: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::StreamCounter)
+            .unwrap_or(0);
+        let stream_id = counter + 1;
+
+        let stream = Stream {
+            id: stream_id,
+            customer: customer.clone(),
+            merchant: merchant.clone(),
+            token,
+            deposit,
+            start,
+            end,
+            withdrawn: 0,
+            status: StreamStatus::Active,
+            cancelled_at: None,
+            created_at: now,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Stream(stream_id), &stream);
+        env.storage()
+            .instance()
+            .set(&DataKey::StreamCounter, &stream_id);
+
+        StreamCreated {
+            stream_id,
+            customer,
+            merchant,
+            deposit,
+            start,
+            end,
+        }
+        .publish(&env);
+
+        Ok(stream_id)
+    }
+
+    pub fn get_stream(env: Env, stream_id: u64) -> Result<Stream, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Stream(stream_id))
+            .ok_or(Error::StreamNotFound)
+    }
+
+    pub fn streamed_amount(env: Env, stream_id: u64) -> Result<i128, Error> {
+        // Includes what the merchant has already withdrawn
+        let stream = Self::get_stream(env.clone(), stream_id)?;
+        Ok(vested(&env, &stream))
+    }
+
+    pub fn withdraw_from_stream(
+        env: Env,
+        merchant: Address,
+        stream_id: u64,
+    ) -> Result<i128, Error> {
+        merchant.require_auth();
+
+        let mut stream = Self::get_stream(env.clone(), stream_id)?;
+        if merchant != stream.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if stream.status != StreamStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+
+        let amount = vested(&env, &stream) - stream.withdrawn;
+        if amount == 0 {
+            return Err(Error::InsufficientBalance);
+        }
+
+        stream.withdrawn += amount;
+        if stream.withdrawn == stream.deposit {
+            stream.status = StreamStatus::Completed;
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::Stream(stream_id), &stream);
+
+        token::Client::new(&env, &stream.token).transfer(
+            &env.current_contract_address(),
+            &merchant,
+            &amount,
+        );
+
+        StreamWithdrawn {
+            stream_id,
+            merchant,
+            amount,
+        }
+        .publish(&env);
+
+        Ok(amount)
+    }
+
+    pub fn cancel_stream(env: Env, caller: Address, stream_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        let mut stream = Self::get_stream(env.clone(), stream_id)?;
+        if caller != stream.customer && caller != stream.merchant {
+            return Err(Error::Unauthorized);
+        }
+        if stream.status != StreamStatus::Active {
+            return Err(Error::InvalidStatus);
+        }
+
+        // The merchant keeps what has vested; the unvested rest goes back to the customer
+        let vested = vested(&env, &stream);
+        let merchant_amount = vested - stream.withdrawn;
+        let customer_amount = stream.deposit - vested;
+
+        stream.withdrawn = vested;
+        stream.status = StreamStatus::Cancelled;
+        stream.cancelled_at = Some(env.ledger().timestamp());
+        env.storage()
             .instance()
-            .set(&DataKey::Payment(payment_id), &payment);
+            .set(&DataKey::Stream(stream_id), &stream);
 
-        let timestamp = env.ledger().timestamp();
-        PaymentCancelled {
-            payment_id,
+        let client = token::Client::new(&env, &stream.token);
+        let contract = env.current_contract_address();
+        if merchant_amount > 0 {
+            client.transfer(&contract, &stream.merchant, &merchant_amount);
+        }
+        if customer_amount > 0 {
+            client.transfer(&contract, &stream.customer, &customer_amount);
+        }
+
+        StreamCancelled {
+            stream_id,
             cancelled_by: caller,
-            timestamp,
-        };
+            merchant_amount,
+            customer_amount,
+        }
+        .publish(&env);
 
         Ok(())
     }
+
+    pub fn create_split_payment(
+        env: Env,
+        customer: Address,
+        token: Address,
+        parts: Vec<(Address, i128)>,
+    ) -> Result<u64, Error> {
+        customer.require_auth();
+
+        if parts.is_empty() || parts.len() > MAX_SPLIT_PARTS {
+            return Err(Error::InvalidAmount);
+        }
+        let mut total: i128 = 0;
+        for (_, amount) in parts.iter() {
+            if amount <= 0 {
+                return Err(Error::InvalidAmount);
+            }
+            total = total.checked_add(amount).ok_or(Error::InvalidAmount)?;
+        }
+
+        // The whole checkout is taken once and held until each child settles
+        token::Client::new(&env, &token).transfer(
+            &customer,
+            env.current_contract_address(),
+            &total,
+        );
+
+        let counter: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::SplitPaymentCounter)
+            .unwrap_or(0);
+        let split_id = counter + 1;
+
+        let mut payment_ids = Vec::new(&env);
+        for (merchant, amount) in parts.iter() {
+            let mut payment = new_payment(
+                &env,
+                customer.clone(),
+                merchant.clone(),
+                amount,
+                token.clone(),
+            );
+            payment.dispute_window = Self::get_dispute_window(env.clone(), merchant);
+            payment.split_id = Some(split_id);
+            save_payment(&env, &payment);
+            payment_ids.push_back(payment.id);
+        }
+
+        let split = SplitPayment {
+            id: split_id,
+            customer: customer.clone(),
+            token,
+            total,
+            payment_ids: payment_ids.clone(),
+            created_at: env.ledger().timestamp(),
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::SplitPayment(split_id), &split);
+        env.storage()
+            .instance()
+            .set(&DataKey::SplitPaymentCounter, &split_id);
+
+        SplitPaymentCreated {
+            split_id,
+            customer,
+            total,
+            payment_ids,
+        }
+        .publish(&env);
+
+        Ok(split_id)
+    }
+
+    pub fn get_split_payment(env: Env, split_id: u64) -> Result<SplitPayment, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::SplitPayment(split_id))
+            .ok_or(Error::SplitPaymentNotFound)
+    }
+
+    pub fn get_split_payment_status(env: Env, split_id: u64) -> Result<SplitPaymentStatus, Error> {
+        let split = Self::get_split_payment(env.clone(), split_id)?;
+
+        // The parent has no status of its own; it is read off the children
+        let mut pending = 0;
+        let mut completed = 0;
+        let mut refunded = 0;
+        let mut cancelled = 0;
+        for payment_id in split.payment_ids.iter() {
+            match Self::get_payment(&env, payment_id).status {
+                PaymentStatus::Pending => pending += 1,
+                PaymentStatus::Completed | PaymentStatus::InEscrow => completed += 1,
+                PaymentStatus::Refunded => refunded += 1,
+                PaymentStatus::Cancelled => cancelled += 1,
+            }
+        }
+
+        let children = split.payment_ids.len();
+        let status = if pending == children {
+            SplitPaymentStatus::Pending
+        } else if pending > 0 {
+            SplitPaymentStatus::PartiallySettled
+        } else if completed == children {
+            SplitPaymentStatus::Completed
+        } else if refunded == children {
+            SplitPaymentStatus::Refunded
+        } else if cancelled == children {
+            SplitPaymentStatus::Cancelled
+        } else {
+            SplitPaymentStatus::Settled
+        };
+
+        Ok(status)
+    }
+
+    pub fn get_payment(env: &Env, payment_id: u64) -> Payment {
+        env.storage()
+            .instance()
+            .get(&DataKey::Payment(payment_id))
+            .expect("Payment not found")
+    }
+
+    pub fn get_payment_settlement(env: Env, payment_id: u64) -> Option<Settlement> {
+        env.storage()
+            .instance()
+            .get(&DataKey::PaymentSettlement(payment_id))
+    }
+
+    pub fn complete_payment(env: Env, admin: Address, payment_id: u64) -> Result<(), Error> {
+        check_admin(&env, &admin)?;
+
+        complete(&env, payment_id)
+    }
+
+    pub fn refund_payment(env: Env, admin: Address, payment_id: u64) -> Result<(), Error> {
+        check_admin(&env, &admin)?;
+
+        refund(&env, payment_id)
+    }
+
+    pub fn cancel_payment(env: Env, caller: Address, payment_id: u64) -> Result<(), Error> {
+        caller.require_auth();
+
+        cancel(&env, &caller, payment_id)
+    }
+
+    pub fn batch_complete_payments(
+        env: Env,
+        admin: Address,
+        payment_ids: Vec<u64>,
+    ) -> Result<Vec<BatchResult>, Error> {
+        check_admin(&env, &admin)?;
+
+        run_batch(&env, &payment_ids, |id| complete(&env, id))
+    }
+
+    pub fn batch_refund_payments(
+        env: Env,
+        admin: Address,
+        payment_ids: Vec<u64>,
+    ) -> Result<Vec<BatchResult>, Error> {
+        check_admin(&env, &admin)?;
+
+        run_batch(&env, &payment_ids, |id| refund(&env, id))
+    }
+}
+
+fn complete(env: &Env, payment_id: u64) -> Result<(), Error> {
+    // Check if payment exists
+    if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
+        return Err(Error::PaymentNotFound);
+    }
+
+    let mut payment = PaymentContract::get_payment(env, payment_id);
+
+    match payment.status {
+        PaymentStatus::Pending => {}
+        PaymentStatus::Completed => return Err(Error::AlreadyProcessed),
+        PaymentStatus::InEscrow => return Err(Error::AlreadyProcessed),
+        PaymentStatus::Refunded => return Err(Error::InvalidStatus),
+        PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
+    }
+
+    if let Some(dispute_window) = payment.dispute_window {
+        // The platform takes its fee up front and only the rest goes into escrow
+        let fee = platform_fee(env, payment.amount, false);
+        let escrowed = payment.amount - fee;
+        let escrow_id = move_to_escrow(env, &payment, escrowed, dispute_window)?;
+        charge_platform_fee(env, &payment, fee);
+        payment.fee += fee;
+        payment.status = PaymentStatus::InEscrow;
+        payment.escrow_id = Some(escrow_id);
+
+        PaymentEscrowed {
+            payment_id,
+            escrow_id,
+            merchant: payment.merchant.clone(),
+            amount: escrowed,
+            dispute_window,
+        }
+        .publish(env);
+    } else {
+        credit_merchant(env, &mut payment)?;
+        payment.status = PaymentStatus::Completed;
+    }
+
+    // Tips are paid out on their own and never go through escrow or settlement. They are
+    // paid last so a failure above cannot leave a tip paid on a payment still pending
+    pay_tip(env, &mut payment);
+    env.storage()
+        .instance()
+        .set(&DataKey::Payment(payment_id), &payment);
+
+    PaymentCompleted {
+        payment_id,
+        merchant: payment.merchant,
+        amount: payment.amount,
+        tip: payment.tip_amount,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn refund(env: &Env, payment_id: u64) -> Result<(), Error> {
+    // Check if payment exists
+    if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
+        return Err(Error::PaymentNotFound);
+    }
+
+    let mut payment = PaymentContract::get_payment(env, payment_id);
+
+    let converted = env
+        .storage()
+        .instance()
+        .has(&DataKey::PaymentSettlement(payment_id));
+
+    match payment.status {
+        PaymentStatus::Pending => return_tip(env, &payment),
+        // Converted funds cannot be returned in the token the customer paid with
+        PaymentStatus::Completed if converted => return Err(Error::InvalidStatus),
+        // Completed funds still sitting in the merchant balance can be clawed back; the
+        // platform fee is not returned, so the merchant's balance covers the full amount
+        PaymentStatus::Completed => debit_merchant(env, &payment)?,
+        PaymentStatus::InEscrow => return Err(Error::InvalidStatus),
+        PaymentStatus::Refunded => return Err(Error::AlreadyProcessed),
+        PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
+    }
+
+    payment.status = PaymentStatus::Refunded;
+    reopen_invoice(env, &payment);
+    return_held_funds(env, &payment);
+
+    env.storage()
+        .instance()
+        .set(&DataKey::Payment(payment_id), &payment);
+
+    PaymentRefunded {
+        payment_id,
+        customer: payment.customer,
+        amount: payment.amount,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn cancel(env: &Env, caller: &Address, payment_id: u64) -> Result<(), Error> {
+    // Check if payment exists
+    if !env.storage().instance().has(&DataKey::Payment(payment_id)) {
+        return Err(Error::PaymentNotFound);
+    }
+
+    let mut payment = PaymentContract::get_payment(env, payment_id);
+
+    // Check authorization: caller must be customer, merchant, or admin
+    let is_authorized = &payment.customer == caller || &payment.merchant == caller;
+    if !is_authorized {
+        return Err(Error::Unauthorized);
+    }
+
+    // Check payment status is Pending
+    match payment.status {
+        PaymentStatus::Pending => {
+            payment.status = PaymentStatus::Cancelled;
+            return_tip(env, &payment);
+        }
+        PaymentStatus::Completed => return Err(Error::InvalidStatus),
+        PaymentStatus::InEscrow => return Err(Error::InvalidStatus),
+        PaymentStatus::Refunded => return Err(Error::InvalidStatus),
+        PaymentStatus::Cancelled => return Err(Error::InvalidStatus),
+    }
+
+    reopen_invoice(env, &payment);
+    return_held_funds(env, &payment);
+
+    env.storage()
+        .instance()
+        .set(&DataKey::Payment(payment_id), &payment);
+
+    let timestamp = env.ledger().timestamp();
+    PaymentCancelled {
+        payment_id,
+        cancelled_by: caller.clone(),
+        timestamp,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn vested(env: &Env, stream: &Stream) -> i128 {
+    // Vesting stops at cancellation
+    let now = stream
+        .cancelled_at
+        .unwrap_or_else(|| env.ledger().timestamp());
+    if now <= stream.start {
+        0
+    } else if now >= stream.end {
+        stream.deposit
+    } else {
+        stream.deposit * (now - stream.start) as i128 / (stream.end - stream.start) as i128
+    }
+}
+
+fn require_admin(env: &Env) -> Result<Address, Error> {
+    let admin: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)?;
+    admin.require_auth();
+    Ok(admin)
+}
+
+fn check_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+    admin.require_auth();
+
+    // Any address can sign for itself, so it also has to be the one set at initialize
+    let stored: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(Error::NotInitialized)?;
+    if admin != &stored {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+fn load_balance(env: &Env, merchant: &Address, token: &Address) -> MerchantBalance {
+    let mut balance = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantBalance(merchant.clone(), token.clone()))
+        .unwrap_or(MerchantBalance {
+            available: 0,
+            pending: 0,
+            pending_until: 0,
+        });
+
+    // Credits become available once the settlement period they landed in has ended
+    if env.ledger().timestamp() >= balance.pending_until {
+        balance.available += balance.pending;
+        balance.pending = 0;
+    }
+    balance
+}
+
+fn save_balance(env: &Env, merchant: &Address, token: &Address, balance: &MerchantBalance) {
+    env.storage().instance().set(
+        &DataKey::MerchantBalance(merchant.clone(), token.clone()),
+        balance,
+    );
+}
+
+// The end of the settlement period under way, fixed when a credit lands so a later
+// config change does not move it
+fn period_end(env: &Env) -> u64 {
+    let period = PaymentContract::get_settlement_config(env.clone()).period;
+    (env.ledger().timestamp() / period)
+        .saturating_add(1)
+        .saturating_mul(period)
+}
+
+fn credit_merchant(env: &Env, payment: &mut Payment) -> Result<(), Error> {
+    let fee = platform_fee(env, payment.amount, false);
+    let net = payment.amount - fee;
+
+    let (token, amount) =
+        match PaymentContract::get_settlement_token(env.clone(), payment.merchant.clone()) {
+            Some(preference) if preference.token != payment.token => {
+                let amount = swap_to_settlement(env, payment, net, &preference)?;
+                env.storage().instance().set(
+                    &DataKey::PaymentSettlement(payment.id),
+                    &Settlement {
+                        token: preference.token.clone(),
+                        amount,
+                    },
+                );
+                (preference.token, amount)
+            }
+            _ => (payment.token.clone(), net),
+        };
+
+    // The fee is only credited once the swap, if any, has gone through
+    charge_platform_fee(env, payment, fee);
+    payment.fee += fee;
+    credit_balance(env, &payment.merchant, &token, amount);
+
+    MerchantCredited {
+        merchant: payment.merchant.clone(),
+        token,
+        payment_id: payment.id,
+        amount,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn swap_to_settlement(
+    env: &Env,
+    payment: &Payment,
+    amount_in: i128,
+    preference: &SettlementPreference,
+) -> Result<i128, Error> {
+    let config: SwapRouterConfig = env
+        .storage()
+        .instance()
+        .get(&DataKey::SwapRouter)
+        .ok_or(Error::SwapRouterNotConfigured)?;
+
+    // The oracle value less the merchant's slippage tolerance is the least we accept
+    let expected = oracle_swap_value(
+        env,
+        &payment.token,
+        &preference.token,
+        amount_in,
+        &config.price_currency,
+    )?;
+    let min_out =
+        expected * (BPS_DENOMINATOR - preference.max_slippage_bps as i128) / BPS_DENOMINATOR;
+
+    // Measure what actually arrived rather than trusting the router's return value
+    let contract = env.current_contract_address();
+    let token_out = token::Client::new(env, &preference.token);
+    let before = token_out.balance(&contract);
+    authorize_transfer(env, &payment.token, &config.router, amount_in);
+    let swap = SwapRouterClient::new(env, &config.router).try_swap_exact_in(
+        &contract,
+        &payment.token,
+        &preference.token,
+        &amount_in,
+        &min_out,
+        &contract,
+    );
+    // A router that enforces min_out rolls its swap back, leaving the payment untouched
+    if !matches!(swap, Ok(Ok(_))) {
+        return Err(Error::SlippageExceeded);
+    }
+    let amount_out = token_out.balance(&contract) - before;
+    if amount_out < min_out {
+        // The router reported success but under-delivered; the swap already ran, so the
+        // whole invocation has to be rolled back
+        panic_with_error!(env, Error::SlippageExceeded);
+    }
+
+    PaymentSwapped {
+        payment_id: payment.id,
+        token_in: payment.token.clone(),
+        amount_in,
+        token_out: preference.token.clone(),
+        amount_out,
+    }
+    .publish(env);
+
+    Ok(amount_out)
+}
+
+fn quote_fiat(
+    env: &Env,
+    token: &Address,
+    fiat_amount: i128,
+    currency: Symbol,
+) -> Result<FiatQuote, Error> {
+    let (latest, decimals) = oracle_price(env, token, &currency)?;
+
+    Ok(FiatQuote {
+        currency,
+        fiat_amount,
+        price: latest.price,
+        decimals,
+        priced_at: latest.timestamp,
+    })
+}
+
+// Expected output of a swap, valuing both tokens at their oracle price in `currency`
+fn oracle_swap_value(
+    env: &Env,
+    token_in: &Address,
+    token_out: &Address,
+    amount_in: i128,
+    currency: &Symbol,
+) -> Result<i128, Error> {
+    let (price_in, _) = oracle_price(env, token_in, currency)?;
+    let (price_out, _) = oracle_price(env, token_out, currency)?;
+    let decimals_in = token::Client::new(env, token_in).decimals();
+    let decimals_out = token::Client::new(env, token_out).decimals();
+
+    // out = in * price_in / price_out, rescaled from input to output token decimals
+    let numerator = 10_i128
+        .checked_pow(decimals_out)
+        .and_then(|scale| amount_in.checked_mul(scale))
+        .and_then(|scaled| scaled.checked_mul(price_in.price))
+        .ok_or(Error::InvalidAmount)?;
+    let denominator = 10_i128
+        .checked_pow(decimals_in)
+        .and_then(|scale| price_out.price.checked_mul(scale))
+        .ok_or(Error::InvalidAmount)?;
+    Ok(numerator / denominator)
+}
+
+// Latest price of `token` in `currency`, with the oracle's decimals, after the
+// staleness and deviation checks
+fn oracle_price(env: &Env, token: &Address, currency: &Symbol) -> Result<(PriceData, u32), Error> {
+    let config = PaymentContract::get_price_oracle(env.clone(), currency.clone())
+        .ok_or(Error::OracleNotConfigured)?;
+    let oracle = PriceOracleClient::new(env, &config.oracle);
+    let asset = Asset::Stellar(token.clone());
+
+    let latest = oracle
+        .lastprice(&asset)
+        .filter(|data| data.price > 0)
+        .ok_or(Error::PriceUnavailable)?;
+    if env.ledger().timestamp() > latest.timestamp.saturating_add(config.max_age) {
+        return Err(Error::StalePrice);
+    }
+
+    // A sharp move against the previous record suggests a manipulated or broken feed
+    let previous = oracle
+        .prices(&asset, &2)
+        .and_then(|history| history.get(1))
+        .filter(|data| data.price > 0);
+    if let Some(previous) = previous {
+        let deviation = (latest.price - previous.price).abs() * BPS_DENOMINATOR / previous.price;
+        if deviation > config.max_deviation_bps as i128 {
+            return Err(Error::PriceDeviationExceeded);
+        }
+    }
+
+    Ok((latest, oracle.decimals()))
+}
+
+fn fiat_to_token(env: &Env, token: &Address, quote: &FiatQuote) -> Result<i128, Error> {
+    let token_decimals = token::Client::new(env, token).decimals();
+
+    // amount = fiat / price, rescaled from fiat and oracle decimals to token decimals
+    let numerator = 10_i128
+        .checked_pow(quote.decimals + token_decimals)
+        .and_then(|scale| quote.fiat_amount.checked_mul(scale))
+        .ok_or(Error::InvalidAmount)?;
+    let denominator = quote
+        .price
+        .checked_mul(10_i128.pow(FIAT_DECIMALS))
+        .ok_or(Error::InvalidAmount)?;
+
+    // Round up so the merchant never receives less than the fiat price
+    let amount = numerator / denominator + i128::from(numerator % denominator != 0);
+    Ok(amount)
+}
+
+fn authorize_transfer(env: &Env, token: &Address, to: &Address, amount: i128) {
+    // Lets a called contract pull funds from this one in a nested transfer
+    let contract = env.current_contract_address();
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token.clone(),
+                fn_name: symbol_short!("transfer"),
+                args: (contract, to.clone(), amount).into_val(env),
+            },
+            sub_invocations: vec![env],
+        }),
+    ]);
+}
+
+fn credit_balance(env: &Env, account: &Address, token: &Address, amount: i128) {
+    let mut balance = load_balance(env, account, token);
+    balance.pending += amount;
+    balance.pending_until = balance.pending_until.max(period_end(env));
+    save_balance(env, account, token, &balance);
+}
+
+// The platform's cut of `amount`
+fn platform_fee(env: &Env, amount: i128, is_tip: bool) -> i128 {
+    match PaymentContract::get_fee_config(env.clone()) {
+        Some(config) if !(is_tip && config.exempt_tips) => {
+            amount * config.fee_bps as i128 / BPS_DENOMINATOR
+        }
+        _ => 0,
+    }
+}
+
+// Credits a fee worked out by `platform_fee` to the fee recipient
+fn charge_platform_fee(env: &Env, payment: &Payment, fee: i128) {
+    let config = match PaymentContract::get_fee_config(env.clone()) {
+        Some(config) if fee > 0 => config,
+        _ => return,
+    };
+
+    credit_balance(env, &config.recipient, &payment.token, fee);
+
+    PlatformFeeCharged {
+        payment_id: payment.id,
+        recipient: config.recipient,
+        token: payment.token.clone(),
+        amount: fee,
+    }
+    .publish(env);
+}
+
+fn pay_tip(env: &Env, payment: &mut Payment) {
+    if payment.tip_amount == 0 {
+        return;
+    }
+
+    let fee = platform_fee(env, payment.tip_amount, true);
+    charge_platform_fee(env, payment, fee);
+    payment.fee += fee;
+
+    let recipient = payment
+        .tip_recipient
+        .clone()
+        .unwrap_or(payment.merchant.clone());
+    token::Client::new(env, &payment.token).transfer(
+        &env.current_contract_address(),
+        &recipient,
+        &(payment.tip_amount - fee),
+    );
+}
+
+fn return_tip(env: &Env, payment: &Payment) {
+    if payment.tip_amount > 0 {
+        token::Client::new(env, &payment.token).transfer(
+            &env.current_contract_address(),
+            &payment.customer,
+            &payment.tip_amount,
+        );
+    }
+}
+
+fn debit_merchant(env: &Env, payment: &Payment) -> Result<(), Error> {
+    let mut balance = load_balance(env, &payment.merchant, &payment.token);
+    if balance.pending + balance.available < payment.amount {
+        return Err(Error::InsufficientBalance);
+    }
+
+    // Take from unsettled funds first, then from the available balance
+    let from_pending = payment.amount.min(balance.pending);
+    balance.pending -= from_pending;
+    balance.available -= payment.amount - from_pending;
+    save_balance(env, &payment.merchant, &payment.token, &balance);
+
+    Ok(())
+}
+
+fn run_batch(
+    env: &Env,
+    payment_ids: &Vec<u64>,
+    mut apply: impl FnMut(u64) -> Result<(), Error>,
+) -> Result<Vec<BatchResult>, Error> {
+    if payment_ids.len() > MAX_BATCH_SIZE {
+        return Err(Error::BatchTooLarge);
+    }
+
+    // A failing id is reported in its result instead of reverting the batch. Each step
+    // returns its errors before any transfer or credit, or from an escrow hand-off or swap
+    // that rolls itself back, and panics once past that point, so a failed id leaves no trace
+    let mut results = Vec::new(env);
+    for payment_id in payment_ids.iter() {
+        let outcome = apply(payment_id);
+        results.push_back(BatchResult {
+            id: payment_id,
+            success: outcome.is_ok(),
+            error_code: outcome.err().map_or(0, |e| e as u32),
+        });
+    }
+
+    Ok(results)
+}
+
+fn new_payment(
+    env: &Env,
+    customer: Address,
+    merchant: Address,
+    amount: i128,
+    token: Address,
+) -> Payment {
+    let counter: u64 = env
+        .storage()
+        .instance()
+        .get(&DataKey::PaymentCounter)
+        .unwrap_or(0);
+    let payment_id = counter + 1;
+    env.storage()
+        .instance()
+        .set(&DataKey::PaymentCounter, &payment_id);
+
+    Payment {
+        id: payment_id,
+        customer,
+        merchant,
+        amount,
+        token,
+        status: PaymentStatus::Pending,
+        created_at: env.ledger().timestamp(),
+        dispute_window: None,
+        escrow_id: None,
+        subscription_id: None,
+        invoice_id: None,
+        split_id: None,
+        mandate_id: None,
+        quote: PriceQuote::None,
+        tip_amount: 0,
+        tip_recipient: None,
+        fee: 0,
+        order_ref: None,
+    }
+}
+
+fn place_payment(
+    env: &Env,
+    customer: Address,
+    merchant: Address,
+    amount: i128,
+    token: Address,
+) -> Payment {
+    // The contract holds the funds until completion credits the merchant or escrows them
+    token::Client::new(env, &token).transfer(&customer, env.current_contract_address(), &amount);
+
+    let mut payment = new_payment(env, customer, merchant, amount, token);
+    payment.dispute_window =
+        PaymentContract::get_dispute_window(env.clone(), payment.merchant.clone());
+    payment
+}
+
+fn close_invoice(env: &Env, invoice: &Invoice) {
+    env.storage()
+        .instance()
+        .set(&DataKey::Invoice(invoice.id), invoice);
+
+    InvoiceClosed {
+        invoice_id: invoice.id,
+        status: invoice.status.clone(),
+    }
+    .publish(env);
+}
+
+fn reopen_invoice(env: &Env, payment: &Payment) {
+    let invoice: Option<Invoice> = payment
+        .invoice_id
+        .and_then(|invoice_id| env.storage().instance().get(&DataKey::Invoice(invoice_id)));
+
+    // An invoice whose payment was cancelled or refunded is unpaid again
+    if let Some(mut invoice) = invoice {
+        if invoice.status != InvoiceStatus::Paid || invoice.payment_id != Some(payment.id) {
+            return;
+        }
+        invoice.status = InvoiceStatus::Open;
+        invoice.payment_id = None;
+        env.storage()
+            .instance()
+            .set(&DataKey::Invoice(invoice.id), &invoice);
+
+        InvoiceReopened {
+            invoice_id: invoice.id,
+            payment_id: payment.id,
+        }
+        .publish(env);
+    }
+}
+
+fn save_payment(env: &Env, payment: &Payment) {
+    env.storage()
+        .instance()
+        .set(&DataKey::Payment(payment.id), payment);
+}
+
+fn load_subscription_for(
+    env: &Env,
+    caller: &Address,
+    subscription_id: u64,
+) -> Result<Subscription, Error> {
+    caller.require_auth();
+
+    let subscription: Subscription = env
+        .storage()
+        .instance()
+        .get(&DataKey::Subscription(subscription_id))
+        .ok_or(Error::SubscriptionNotFound)?;
+
+    // Either side of the subscription can manage it
+    if caller != &subscription.customer && caller != &subscription.merchant {
+        return Err(Error::Unauthorized);
+    }
+
+    Ok(subscription)
+}
+
+fn save_subscription(env: &Env, subscription: &Subscription, changed_by: Address) {
+    env.storage()
+        .instance()
+        .set(&DataKey::Subscription(subscription.id), subscription);
+
+    SubscriptionStatusChanged {
+        subscription_id: subscription.id,
+        status: subscription.status.clone(),
+        changed_by,
+    }
+    .publish(env);
+}
+
+fn move_to_escrow(
+    env: &Env,
+    payment: &Payment,
+    amount: i128,
+    dispute_window: u64,
+) -> Result<u64, Error> {
+    let escrow_contract: Address = env
+        .storage()
+        .instance()
+        .get(&DataKey::EscrowContract)
+        .ok_or(Error::EscrowNotConfigured)?;
+
+    // The escrow pulls the held funds from this contract
+    let contract = env.current_contract_address();
+    authorize_transfer(env, &payment.token, &escrow_contract, amount);
+
+    // A rejected hand-off is rolled back on the escrow side and reported as an error here
+    match EscrowClient::new(env, &escrow_contract).try_create_escrow_for(
+        &contract,
+        &payment.customer,
+        &payment.merchant,
+        &payment.token,
+        &amount,
+        &dispute_window,
+    ) {
+        Ok(Ok(escrow_id)) => Ok(escrow_id),
+        _ => Err(Error::EscrowFailed),
+    }
+}
+
+fn return_held_funds(env: &Env, payment: &Payment) {
+    token::Client::new(env, &payment.token).transfer(
+        &env.current_contract_address(),
+        &payment.customer,
+        &payment.amount,
+    );
 }
 
+mod mock_oracle;
+mod mock_router;
 mod test;
@@ -0,0 +1,41 @@
+#![cfg(test)]
+
+use crate::{Asset, PriceData};
+use soroban_sdk::{contract, contractimpl, contracttype, Address, Env, Vec};
+
+// Reflector publishes prices with 14 decimals
+const DECIMALS: u32 = 14;
+
+#[derive(Clone)]
+#[contracttype]
+enum DataKey {
+    History(Asset),
+}
+
+// A stand-in for a SEP-40 oracle: records are pushed by the test, newest first
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, token: Address, price: i128, timestamp: u64) {
+        let key = DataKey::History(Asset::Stellar(token));
+        let mut history: Vec<PriceData> =
+            env.storage().instance().get(&key).unwrap_or(Vec::new(&env));
+        history.push_front(PriceData { price, timestamp });
+        env.storage().instance().set(&key, &history);
+    }
+
+    pub fn decimals(_env: Env) -> u32 {
+        DECIMALS
+    }
+
+    pub fn lastprice(env: Env, asset: Asset) -> Option<PriceData> {
+        Self::prices(env, asset, 1).and_then(|history| history.get(0))
+    }
+
+    pub fn prices(env: Env, asset: Asset, records: u32) -> Option<Vec<PriceData>> {
+        let history: Vec<PriceData> = env.storage().instance().get(&DataKey::History(asset))?;
+        Some(history.slice(0..records.min(history.len())))
+    }
+}
@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env};
+
+const BPS_DENOMINATOR: i128 = 10_000;
+
+#[derive(Clone)]
+#[contracttype]
+enum DataKey {
+    Rate,
+    Impact,
+}
+
+// A stand-in for a DEX router: swaps at a fixed rate, less a price impact, and fails when
+// that falls short of min_out. It holds its own liquidity of the output token.
+#[contract]
+pub struct MockRouter;
+
+#[contractimpl]
+impl MockRouter {
+    pub fn set_rate(env: Env, rate_bps: i128, impact_bps: i128) {
+        env.storage().instance().set(&DataKey::Rate, &rate_bps);
+        env.storage().instance().set(&DataKey::Impact, &impact_bps);
+    }
+
+    pub fn swap_exact_in(
+        env: Env,
+        sender: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: i128,
+        min_out: i128,
+        to: Address,
+    ) -> i128 {
+        sender.require_auth();
+
+        token::Client::new(&env, &token_in).transfer(
+            &sender,
+            env.current_contract_address(),
+            &amount_in,
+        );
+
+        let rate: i128 = env.storage().instance().get(&DataKey::Rate).unwrap();
+        let impact: i128 = env.storage().instance().get(&DataKey::Impact).unwrap();
+        let amount_out =
+            amount_in * rate / BPS_DENOMINATOR * (BPS_DENOMINATOR - impact) / BPS_DENOMINATOR;
+        if amount_out < min_out {
+            panic!("output below min_out");
+        }
+        token::Client::new(&env, &token_out).transfer(
+            &env.current_contract_address(),
+            &to,
+            &amount_out,
+        );
+
+        amount_out
+    }
+}
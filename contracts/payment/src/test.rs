@@ -1,7 +1,14 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, Address, Env};
+use crate::mock_oracle::{MockOracle, MockOracleClient};
+use crate::mock_router::{MockRouter, MockRouterClient};
+use ed25519_dalek::{Signer, SigningKey};
+use soroban_sdk::{
+    testutils::storage::Persistent as _, testutils::Address as _, testutils::Events,
+    testutils::Ledger, testutils::MockAuth, testutils::MockAuthInvoke, token, vec, Address, BytesN,
+    Env, IntoVal, Map, String, TryFromVal, Val,
+};
 
 #[test]
 fn test_create_payment() {
@@ -11,10 +18,10 @@ fn test_create_payment() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
     assert_eq!(payment_id, 1);
@@ -35,10 +42,10 @@ fn test_get_payment() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 5000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -71,10 +78,11 @@ fn test_complete_payment_success() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -95,10 +103,11 @@ fn test_refund_payment_success() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 2000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -120,10 +129,66 @@ fn test_complete_payment_not_found() {
     let admin = Address::generate(&env);
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     client.complete_payment(&admin, &999);
 }
 
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_complete_payment_by_non_admin_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+
+    // Only the customer signs, so the stored admin's authorization is missing
+    env.mock_auths(&[MockAuth {
+        address: &customer,
+        invoke: &MockAuthInvoke {
+            contract: &contract_id,
+            fn_name: "complete_payment",
+            args: (admin.clone(), payment_id).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.complete_payment(&admin, &payment_id);
+}
+
+#[test]
+fn test_complete_payment_by_other_signer_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+
+    // The customer signs for itself, but is not the stored admin
+    let result = client.try_complete_payment(&customer, &payment_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::Pending
+    );
+}
+
 #[test]
 #[should_panic]
 fn test_refund_payment_not_found() {
@@ -134,6 +199,7 @@ fn test_refund_payment_not_found() {
     let admin = Address::generate(&env);
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     client.refund_payment(&admin, &999);
 }
@@ -147,10 +213,11 @@ fn test_complete_already_completed_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -171,10 +238,11 @@ fn test_refund_already_refunded_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 2000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -195,10 +263,11 @@ fn test_complete_refunded_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -219,10 +288,11 @@ fn test_refund_completed_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 2000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -244,9 +314,11 @@ fn test_multiple_payments_correct_modification() {
     let customer2 = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer1, 1000);
+    token::StellarAssetClient::new(&env, &token).mint(&customer2, &2000);
+    client.initialize(&admin);
 
     // Create two payments
     let payment_id1 = client.create_payment(&customer1, &merchant, &1000_i128, &token);
@@ -271,10 +343,10 @@ fn test_customer_cancel_pending_payment() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -295,10 +367,10 @@ fn test_merchant_cancel_pending_payment() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -336,10 +408,10 @@ fn test_cancel_payment_unauthorized() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let unauthorized_user = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -358,10 +430,11 @@ fn test_cancel_completed_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -383,10 +456,11 @@ fn test_cancel_refunded_payment() {
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
     let admin = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -407,10 +481,10 @@ fn test_cancel_already_cancelled_payment() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -431,10 +505,10 @@ fn test_cancel_payment_event_emission() {
 
     let customer = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
     let amount = 1000_i128;
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
 
     let payment_id = client.create_payment(&customer, &merchant, &amount, &token);
 
@@ -456,9 +530,10 @@ fn test_cancel_multiple_payments_correct_modification() {
     let customer1 = Address::generate(&env);
     let customer2 = Address::generate(&env);
     let merchant = Address::generate(&env);
-    let token = Address::generate(&env);
 
     env.mock_all_auths();
+    let token = create_token(&env, &customer1, 1000);
+    token::StellarAssetClient::new(&env, &token).mint(&customer2, &2000);
 
     // Create two payments
     let payment_id1 = client.create_payment(&customer1, &merchant, &1000_i128, &token);
@@ -473,4 +548,1758 @@ fn test_cancel_multiple_payments_correct_modification() {
 
     assert_eq!(payment1.status, PaymentStatus::Cancelled);
     assert_eq!(payment2.status, PaymentStatus::Pending);
-}
\ No newline at end of file
+}
+
+const DISPUTE_WINDOW: u64 = 86_400;
+
+fn create_token(env: &Env, holder: &Address, amount: i128) -> Address {
+    let issuer = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(env, &token).mint(holder, &amount);
+    token
+}
+
+// The admin a setup helper initialized the contract with
+fn admin_of(env: &Env, client: &PaymentContractClient) -> Address {
+    env.as_contract(&client.address, || {
+        env.storage().instance().get(&DataKey::Admin).unwrap()
+    })
+}
+
+// Registers a payment contract wired to a fresh escrow contract
+fn setup_escrow_backed(env: &Env) -> (PaymentContractClient<'_>, escrow::EscrowContractClient<'_>) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+    let escrow_id = env.register(escrow::EscrowContract, ());
+    let escrow_client = escrow::EscrowContractClient::new(env, &escrow_id);
+
+    env.mock_all_auths();
+    escrow_client.initialize(&Address::generate(env));
+    client.initialize(&Address::generate(env));
+    client.set_escrow_contract(&escrow_id);
+
+    (client, escrow_client)
+}
+
+#[test]
+fn test_set_dispute_window() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+
+    assert_eq!(client.get_dispute_window(&merchant), None);
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+    assert_eq!(client.get_dispute_window(&merchant), Some(DISPUTE_WINDOW));
+    client.set_dispute_window(&merchant, &None);
+    assert_eq!(client.get_dispute_window(&merchant), None);
+}
+
+#[test]
+fn test_complete_payment_moves_funds_into_escrow() {
+    let env = Env::default();
+    let (client, escrow_client) = setup_escrow_backed(&env);
+    let admin = admin_of(&env, &client);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let token_client = token::Client::new(&env, &token);
+
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+
+    // The payment contract holds the funds until completion
+    assert_eq!(token_client.balance(&customer), 0);
+    assert_eq!(token_client.balance(&client.address), 1000);
+
+    client.complete_payment(&admin, &payment_id);
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.status, PaymentStatus::InEscrow);
+    let escrow_id = payment.escrow_id.unwrap();
+    assert_eq!(token_client.balance(&client.address), 0);
+    assert_eq!(token_client.balance(&escrow_client.address), 1000);
+
+    let escrow = escrow_client.get_escrow(&escrow_id);
+    assert_eq!(escrow.customer, customer);
+    assert_eq!(escrow.merchant, merchant);
+    assert_eq!(escrow.amount, 1000);
+    assert_eq!(
+        escrow.release_after,
+        env.ledger().timestamp() + DISPUTE_WINDOW
+    );
+
+    let result = client.try_complete_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::AlreadyProcessed)));
+
+    // The merchant collects from the escrow once the window passes
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + DISPUTE_WINDOW);
+    escrow_client.claim_release(&merchant, &escrow_id);
+    assert_eq!(token_client.balance(&merchant), 1000);
+}
+
+#[test]
+fn test_complete_escrow_payment_without_escrow_contract_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+
+    let result = client.try_complete_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::EscrowNotConfigured)));
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::Pending
+    );
+}
+
+#[test]
+fn test_cancel_escrow_backed_payment_returns_funds() {
+    let env = Env::default();
+    let (client, _escrow_client) = setup_escrow_backed(&env);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    client.cancel_payment(&customer, &payment_id);
+
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::Cancelled
+    );
+    assert_eq!(token::Client::new(&env, &token).balance(&customer), 1000);
+}
+
+const MONTH: u64 = 30 * 24 * 60 * 60;
+
+// Everything credited to a merchant, whether or not it has settled yet
+fn credited(client: &PaymentContractClient, merchant: &Address, token: &Address) -> i128 {
+    let balance = client.get_merchant_balance(merchant, token);
+    balance.available + balance.pending
+}
+
+#[test]
+fn test_subscription_charges_once_per_interval() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+    let token_client = token::Client::new(&env, &token);
+
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &3);
+    assert_eq!(token_client.allowance(&customer, &contract_id), 300);
+
+    let payment_id = client.charge_subscription(&subscription_id);
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.status, PaymentStatus::Completed);
+    assert_eq!(payment.subscription_id, Some(subscription_id));
+    assert_eq!(credited(&client, &merchant, &token), 100);
+
+    let result = client.try_charge_subscription(&subscription_id);
+    assert_eq!(result, Err(Ok(Error::ChargeNotDue)));
+
+    for _ in 0..2 {
+        env.ledger().set_timestamp(env.ledger().timestamp() + MONTH);
+        client.charge_subscription(&subscription_id);
+    }
+
+    let subscription = client.get_subscription(&subscription_id);
+    assert_eq!(subscription.periods_charged, 3);
+    assert_eq!(subscription.status, SubscriptionStatus::Completed);
+    assert_eq!(credited(&client, &merchant, &token), 300);
+    assert_eq!(token_client.balance(&customer), 700);
+
+    env.ledger().set_timestamp(env.ledger().timestamp() + MONTH);
+    let result = client.try_charge_subscription(&subscription_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_customer_cannot_refund_own_subscription_charge() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &3);
+    let payment_id = client.charge_subscription(&subscription_id);
+
+    // Only the customer signs, so the stored admin's authorization is missing
+    env.mock_auths(&[MockAuth {
+        address: &customer,
+        invoke: &MockAuthInvoke {
+            contract: &contract_id,
+            fn_name: "refund_payment",
+            args: (payment_id,).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.refund_payment(&admin, &payment_id);
+}
+
+#[test]
+fn test_pause_and_resume_subscription() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &12);
+    client.charge_subscription(&subscription_id);
+
+    client.pause_subscription(&customer, &subscription_id);
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + 3 * MONTH);
+    let result = client.try_charge_subscription(&subscription_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+
+    // Months spent paused are not billed after resuming
+    client.resume_subscription(&merchant, &subscription_id);
+    client.charge_subscription(&subscription_id);
+    let result = client.try_charge_subscription(&subscription_id);
+    assert_eq!(result, Err(Ok(Error::ChargeNotDue)));
+    assert_eq!(credited(&client, &merchant, &token), 200);
+}
+
+#[test]
+fn test_cancel_subscription() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let outsider = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &12);
+
+    let result = client.try_cancel_subscription(&outsider, &subscription_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.cancel_subscription(&merchant, &subscription_id);
+    assert_eq!(
+        client.get_subscription(&subscription_id).status,
+        SubscriptionStatus::Cancelled
+    );
+
+    let result = client.try_charge_subscription(&subscription_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+    let result = client.try_resume_subscription(&customer, &subscription_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_customer_cancel_returns_unused_allowance() {
+    let env = Env::default();
+    let (client, customer, merchant, token, _mandate_id) = setup_mandate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &3);
+    client.charge_subscription(&subscription_id);
+    assert_eq!(token_client.allowance(&customer, &client.address), 950);
+
+    // Only the two uncharged periods come off; the mandate keeps its share
+    client.cancel_subscription(&customer, &subscription_id);
+    assert_eq!(token_client.allowance(&customer, &client.address), 750);
+}
+
+#[test]
+fn test_create_subscription_with_invalid_terms_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+
+    let result = client.try_create_subscription(&customer, &merchant, &token, &0_i128, &MONTH, &12);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+    let result = client.try_create_subscription(&customer, &merchant, &token, &100_i128, &0, &12);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+    let result =
+        client.try_create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &0);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_pay_invoice_creates_linked_payment() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let order_ref = String::from_str(&env, "ORDER-1042");
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+
+    let invoice_id =
+        client.create_invoice(&merchant, &token, &1500_i128, &MONTH, &order_ref, &None);
+    let invoice = client.get_invoice(&invoice_id);
+    assert_eq!(invoice.status, InvoiceStatus::Open);
+    assert_eq!(invoice.order_ref, order_ref);
+
+    let payment_id = client.pay_invoice(&customer, &invoice_id);
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.customer, customer);
+    assert_eq!(payment.merchant, merchant);
+    assert_eq!(payment.amount, 1500);
+    assert_eq!(payment.invoice_id, Some(invoice_id));
+
+    let invoice = client.get_invoice(&invoice_id);
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert_eq!(invoice.payment_id, Some(payment_id));
+
+    let result = client.try_pay_invoice(&customer, &invoice_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_invoice_reopens_when_payment_cancelled_or_refunded() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let order_ref = String::from_str(&env, "ORDER-1043");
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let invoice_id =
+        client.create_invoice(&merchant, &token, &1500_i128, &MONTH, &order_ref, &None);
+
+    // Cancelling the linked payment leaves the invoice unpaid
+    let payment_id = client.pay_invoice(&customer, &invoice_id);
+    client.cancel_payment(&customer, &payment_id);
+    let invoice = client.get_invoice(&invoice_id);
+    assert_eq!(invoice.status, InvoiceStatus::Open);
+    assert_eq!(invoice.payment_id, None);
+
+    // So does refunding it
+    let payment_id = client.pay_invoice(&customer, &invoice_id);
+    client.refund_payment(&admin, &payment_id);
+    let invoice = client.get_invoice(&invoice_id);
+    assert_eq!(invoice.status, InvoiceStatus::Open);
+    assert_eq!(invoice.payment_id, None);
+
+    // A completed payment keeps it paid
+    let payment_id = client.pay_invoice(&customer, &invoice_id);
+    client.complete_payment(&admin, &payment_id);
+    let invoice = client.get_invoice(&invoice_id);
+    assert_eq!(invoice.status, InvoiceStatus::Paid);
+    assert_eq!(invoice.payment_id, Some(payment_id));
+}
+
+#[test]
+fn test_restricted_invoice_rejects_other_payers() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let payer = Address::generate(&env);
+    let someone_else = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &payer, 100_000);
+
+    let invoice_id = client.create_invoice(
+        &merchant,
+        &token,
+        &1500_i128,
+        &MONTH,
+        &String::from_str(&env, "ORDER-1043"),
+        &Some(payer.clone()),
+    );
+
+    let result = client.try_pay_invoice(&someone_else, &invoice_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.pay_invoice(&payer, &invoice_id);
+    assert_eq!(client.get_invoice(&invoice_id).status, InvoiceStatus::Paid);
+}
+
+#[test]
+fn test_overdue_invoice_expires() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+
+    let invoice_id = client.create_invoice(
+        &merchant,
+        &token,
+        &1500_i128,
+        &MONTH,
+        &String::from_str(&env, "ORDER-1044"),
+        &None,
+    );
+
+    let result = client.try_expire_invoice(&invoice_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+
+    env.ledger().set_timestamp(MONTH + 1);
+    let result = client.try_pay_invoice(&customer, &invoice_id);
+    assert_eq!(result, Err(Ok(Error::InvoiceExpired)));
+
+    client.expire_invoice(&invoice_id);
+    assert_eq!(
+        client.get_invoice(&invoice_id).status,
+        InvoiceStatus::Expired
+    );
+}
+
+#[test]
+fn test_void_invoice() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+
+    let invoice_id = client.create_invoice(
+        &merchant,
+        &token,
+        &1500_i128,
+        &MONTH,
+        &String::from_str(&env, "ORDER-1045"),
+        &None,
+    );
+
+    let result = client.try_void_invoice(&customer, &invoice_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.void_invoice(&merchant, &invoice_id);
+    assert_eq!(
+        client.get_invoice(&invoice_id).status,
+        InvoiceStatus::Voided
+    );
+
+    let result = client.try_pay_invoice(&customer, &invoice_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_split_payment_children_settle_independently() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let seller1 = Address::generate(&env);
+    let seller2 = Address::generate(&env);
+    let seller3 = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+    let token_client = token::Client::new(&env, &token);
+
+    let parts = vec![
+        &env,
+        (seller1.clone(), 500_i128),
+        (seller2.clone(), 300_i128),
+        (seller3.clone(), 200_i128),
+    ];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+
+    // Funds are taken once for the whole checkout
+    assert_eq!(token_client.balance(&customer), 0);
+    assert_eq!(token_client.balance(&contract_id), 1000);
+
+    let split = client.get_split_payment(&split_id);
+    assert_eq!(split.total, 1000);
+    assert_eq!(split.payment_ids.len(), 3);
+    assert_eq!(
+        client.get_split_payment_status(&split_id),
+        SplitPaymentStatus::Pending
+    );
+
+    let first = split.payment_ids.get(0).unwrap();
+    assert_eq!(client.get_payment(&first).split_id, Some(split_id));
+    client.complete_payment(&admin, &first);
+    assert_eq!(credited(&client, &seller1, &token), 500);
+    assert_eq!(
+        client.get_split_payment_status(&split_id),
+        SplitPaymentStatus::PartiallySettled
+    );
+
+    client.refund_payment(&admin, &split.payment_ids.get(1).unwrap());
+    client.cancel_payment(&seller3, &split.payment_ids.get(2).unwrap());
+    assert_eq!(token_client.balance(&customer), 500);
+    assert_eq!(token_client.balance(&contract_id), 500);
+    assert_eq!(
+        client.get_split_payment_status(&split_id),
+        SplitPaymentStatus::Settled
+    );
+}
+
+#[test]
+fn test_split_payment_completed_when_all_children_complete() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let seller1 = Address::generate(&env);
+    let seller2 = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+
+    let parts = vec![&env, (seller1, 600_i128), (seller2, 400_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+
+    for payment_id in client.get_split_payment(&split_id).payment_ids.iter() {
+        client.complete_payment(&admin, &payment_id);
+    }
+    assert_eq!(
+        client.get_split_payment_status(&split_id),
+        SplitPaymentStatus::Completed
+    );
+}
+
+#[test]
+fn test_split_payment_with_invalid_parts_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let seller = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+
+    let result = client.try_create_split_payment(&customer, &token, &vec![&env]);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+
+    let parts = vec![&env, (seller.clone(), 600_i128), (seller, 0_i128)];
+    let result = client.try_create_split_payment(&customer, &token, &parts);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+
+    let result = client.try_get_split_payment(&1);
+    assert_eq!(result.err(), Some(Ok(Error::SplitPaymentNotFound)));
+}
+
+fn error_codes(env: &Env, results: &Vec<BatchResult>) -> Vec<u32> {
+    let mut codes = Vec::new(env);
+    for result in results.iter() {
+        assert_eq!(result.success, result.error_code == 0);
+        codes.push_back(result.error_code);
+    }
+    codes
+}
+
+#[test]
+fn test_batch_complete_payments_reports_each_result() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let first = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    let second = client.create_payment(&customer, &merchant, &2000_i128, &token);
+    let cancelled = client.create_payment(&customer, &merchant, &3000_i128, &token);
+    client.cancel_payment(&customer, &cancelled);
+
+    // One bad id does not stop the rest of the batch
+    let ids = vec![&env, first, second, first, cancelled, 999];
+    let results = client.batch_complete_payments(&admin, &ids);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![
+            &env,
+            0,
+            0,
+            Error::AlreadyProcessed as u32,
+            Error::InvalidStatus as u32,
+            Error::PaymentNotFound as u32
+        ]
+    );
+    assert_eq!(results.get(1).unwrap().id, second);
+    assert_eq!(client.get_payment(&first).status, PaymentStatus::Completed);
+    assert_eq!(client.get_payment(&second).status, PaymentStatus::Completed);
+}
+
+#[test]
+fn test_batch_refund_payments() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let first = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    let second = client.create_payment(&customer, &merchant, &2000_i128, &token);
+    client.complete_payment(&admin, &second);
+
+    let results = client.batch_refund_payments(&admin, &vec![&env, first, second, 999]);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![&env, 0, 0, Error::PaymentNotFound as u32]
+    );
+    assert_eq!(client.get_payment(&first).status, PaymentStatus::Refunded);
+    assert_eq!(client.get_payment(&second).status, PaymentStatus::Refunded);
+    assert_eq!(token::Client::new(&env, &token).balance(&customer), 100_000);
+}
+
+#[test]
+fn test_batch_over_limit_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let ids = Vec::from_array(&env, [1u64; 51]);
+    let result = client.try_batch_complete_payments(&admin, &ids);
+    assert_eq!(result.err(), Some(Ok(Error::BatchTooLarge)));
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_batch_complete_payments_by_non_admin_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 100_000);
+    client.initialize(&admin);
+
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    let ids = vec![&env, payment_id];
+
+    // Only the merchant signs, so the stored admin's authorization is missing
+    env.mock_auths(&[MockAuth {
+        address: &merchant,
+        invoke: &MockAuthInvoke {
+            contract: &contract_id,
+            fn_name: "batch_complete_payments",
+            args: (admin.clone(), ids.clone()).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.batch_complete_payments(&admin, &ids);
+}
+
+#[test]
+fn test_batch_reports_rejected_escrow_hand_off() {
+    let env = Env::default();
+    let (client, escrow_client) = setup_escrow_backed(&env);
+    let admin = admin_of(&env, &client);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let escrow_merchant = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    let token_client = token::Client::new(&env, &token);
+
+    // The escrow contract rejects a window that would overflow its release time
+    env.ledger().set_timestamp(1);
+    client.set_dispute_window(&escrow_merchant, &Some(u64::MAX));
+    let held = client.create_payment(&customer, &escrow_merchant, &400_i128, &token);
+    let plain = client.create_payment(&customer, &merchant, &100_i128, &token);
+
+    let results = client.batch_complete_payments(&admin, &vec![&env, held, plain]);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![&env, Error::EscrowFailed as u32, 0]
+    );
+    assert_eq!(client.get_payment(&held).status, PaymentStatus::Pending);
+    assert_eq!(client.get_payment(&plain).status, PaymentStatus::Completed);
+    assert_eq!(token_client.balance(&escrow_client.address), 0);
+    assert_eq!(token_client.balance(&client.address), 500);
+}
+
+#[test]
+fn test_failed_batch_items_leave_no_side_effects() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let platform = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+    let token_client = token::Client::new(&env, &token);
+    client.set_fee_config(&Some(FeeConfig {
+        recipient: platform.clone(),
+        fee_bps: 1000,
+        exempt_tips: false,
+    }));
+
+    // The merchant wants a settlement token but no router is configured
+    client.set_settlement_token(
+        &merchant,
+        &Some(SettlementPreference {
+            token: Address::generate(&env),
+            max_slippage_bps: 100,
+        }),
+    );
+    let parts = vec![&env, (merchant.clone(), 600_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+    let held = client
+        .get_split_payment(&split_id)
+        .payment_ids
+        .get(0)
+        .unwrap();
+
+    let results = client.batch_complete_payments(&admin, &vec![&env, held]);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![&env, Error::SwapRouterNotConfigured as u32]
+    );
+    assert_eq!(client.get_payment(&held).status, PaymentStatus::Pending);
+    assert_eq!(client.get_payment(&held).fee, 0);
+    assert_eq!(credited(&client, &platform, &token), 0);
+    assert_eq!(credited(&client, &merchant, &token), 0);
+
+    // Once settled and withdrawn, refunding it fails without touching any balance
+    client.set_settlement_token(&merchant, &None);
+    client.complete_payment(&admin, &held);
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + DEFAULT_SETTLEMENT_PERIOD);
+    client.withdraw(&merchant, &token, &540, &merchant);
+
+    let results = client.batch_refund_payments(&admin, &vec![&env, held]);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![&env, Error::InsufficientBalance as u32]
+    );
+    assert_eq!(client.get_payment(&held).status, PaymentStatus::Completed);
+    assert_eq!(token_client.balance(&customer), 400);
+    assert_eq!(credited(&client, &platform, &token), 60);
+}
+
+#[test]
+fn test_completed_payments_settle_into_merchant_balance() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let destination = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+
+    let parts = vec![&env, (merchant.clone(), 1000_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+    client.complete_payment(
+        &admin,
+        &client
+            .get_split_payment(&split_id)
+            .payment_ids
+            .get(0)
+            .unwrap(),
+    );
+
+    // Credits wait out the current settlement period
+    let balance = client.get_merchant_balance(&merchant, &token);
+    assert_eq!(balance.pending, 1000);
+    assert_eq!(balance.withdrawable, 0);
+    let result = client.try_withdraw(&merchant, &token, &1000, &destination);
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + DEFAULT_SETTLEMENT_PERIOD);
+    let balance = client.get_merchant_balance(&merchant, &token);
+    assert_eq!(balance.available, 1000);
+    assert_eq!(balance.withdrawable, 1000);
+
+    client.withdraw(&merchant, &token, &400, &destination);
+    assert_eq!(token::Client::new(&env, &token).balance(&destination), 400);
+    assert_eq!(
+        client.get_merchant_balance(&merchant, &token).available,
+        600
+    );
+}
+
+#[test]
+fn test_settlement_config_change_keeps_release_time() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let week = 7 * DEFAULT_SETTLEMENT_PERIOD;
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+    env.ledger().set_timestamp(1_700_000_000);
+
+    // A plain payment credits the merchant, pending until the day ends at 1_700_006_400
+    let payment_id = client.create_payment(&customer, &merchant, &400_i128, &token);
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(client.get_merchant_balance(&merchant, &token).pending, 400);
+
+    // Lengthening the period does not push back funds already pending
+    client.set_settlement_config(&SettlementConfig {
+        period: week,
+        reserve_bps: 0,
+    });
+    env.ledger().set_timestamp(1_700_006_399);
+    assert_eq!(client.get_merchant_balance(&merchant, &token).pending, 400);
+    env.ledger().set_timestamp(1_700_006_400);
+    assert_eq!(
+        client.get_merchant_balance(&merchant, &token).available,
+        400
+    );
+
+    // The next credit waits out the week, ending at 1_700_092_800, and shortening the
+    // period afterwards does not release it early
+    let payment_id = client.create_payment(&customer, &merchant, &600_i128, &token);
+    client.complete_payment(&admin, &payment_id);
+    client.set_settlement_config(&SettlementConfig {
+        period: 60 * 60,
+        reserve_bps: 0,
+    });
+    env.ledger().set_timestamp(1_700_092_799);
+    let balance = client.get_merchant_balance(&merchant, &token);
+    assert_eq!((balance.available, balance.pending), (400, 600));
+    env.ledger().set_timestamp(1_700_092_800);
+    let balance = client.get_merchant_balance(&merchant, &token);
+    assert_eq!((balance.available, balance.pending), (1000, 0));
+}
+
+#[test]
+fn test_reserve_holds_back_funds_for_refunds() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    let token = create_token(&env, &customer, 1000);
+    client.initialize(&admin);
+    client.set_settlement_config(&SettlementConfig {
+        period: 3_600,
+        reserve_bps: 2_000,
+    });
+
+    let parts = vec![
+        &env,
+        (merchant.clone(), 800_i128),
+        (merchant.clone(), 200_i128),
+    ];
+    let split = client.get_split_payment(&client.create_split_payment(&customer, &token, &parts));
+    for payment_id in split.payment_ids.iter() {
+        client.complete_payment(&admin, &payment_id);
+    }
+    env.ledger().set_timestamp(env.ledger().timestamp() + 3_600);
+
+    // A fifth of the balance stays back while refunds are still possible
+    let balance = client.get_merchant_balance(&merchant, &token);
+    assert_eq!(balance.reserved, 200);
+    assert_eq!(balance.withdrawable, 800);
+    client.withdraw(&merchant, &token, &800, &merchant);
+
+    // Refunding a completed payment draws on the merchant balance
+    let result = client.try_refund_payment(&admin, &split.payment_ids.get(0).unwrap());
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+    client.refund_payment(&admin, &split.payment_ids.get(1).unwrap());
+    assert_eq!(token::Client::new(&env, &token).balance(&customer), 200);
+    assert_eq!(client.get_merchant_balance(&merchant, &token).available, 0);
+}
+
+// Payment in one token, settled through the mock router into another. The oracle
+// values the pair at 1:0.5 and the router swaps at `rate_bps` less `impact_bps`.
+fn setup_swap(
+    env: &Env,
+    rate_bps: i128,
+    impact_bps: i128,
+) -> (PaymentContractClient<'_>, Address, Address, Address, u64) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+    let router_id = env.register(MockRouter, ());
+    MockRouterClient::new(env, &router_id).set_rate(&rate_bps, &impact_bps);
+    let oracle_id = env.register(MockOracle, ());
+    let oracle = MockOracleClient::new(env, &oracle_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+
+    env.mock_all_auths();
+    let token = create_token(env, &customer, 1000);
+    let usdc = create_token(env, &router_id, 10_000);
+    client.initialize(&Address::generate(env));
+    oracle.set_price(&token, &TEN_CENTS, &0);
+    oracle.set_price(&usdc, &(2 * TEN_CENTS), &0);
+    client.set_price_oracle(
+        &symbol_short!("USD"),
+        &Some(OracleConfig {
+            oracle: oracle_id,
+            max_age: 300,
+            max_deviation_bps: 500,
+        }),
+    );
+    client.set_swap_router(&SwapRouterConfig {
+        router: router_id,
+        price_currency: symbol_short!("USD"),
+    });
+    client.set_settlement_token(
+        &merchant,
+        &Some(SettlementPreference {
+            token: usdc.clone(),
+            max_slippage_bps: 100,
+        }),
+    );
+
+    let parts = vec![env, (merchant.clone(), 1000_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+    let payment_id = client
+        .get_split_payment(&split_id)
+        .payment_ids
+        .get(0)
+        .unwrap();
+
+    (client, merchant, token, usdc, payment_id)
+}
+
+#[test]
+fn test_complete_payment_swaps_into_settlement_token() {
+    let env = Env::default();
+    let (client, merchant, token, usdc, payment_id) = setup_swap(&env, 5_000, 50);
+    let admin = admin_of(&env, &client);
+
+    client.complete_payment(&admin, &payment_id);
+
+    // Half a percent of impact is inside the merchant's one percent tolerance
+    assert_eq!(
+        client.get_payment_settlement(&payment_id),
+        Some(Settlement {
+            token: usdc.clone(),
+            amount: 497
+        })
+    );
+    assert_eq!(credited(&client, &merchant, &usdc), 497);
+    assert_eq!(credited(&client, &merchant, &token), 0);
+    assert_eq!(
+        token::Client::new(&env, &usdc).balance(&client.address),
+        497
+    );
+
+    // Converted payments cannot be refunded in the original token
+    let result = client.try_refund_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_plain_payment_swaps_into_settlement_token() {
+    let env = Env::default();
+    let (client, merchant, token, usdc, _payment_id) = setup_swap(&env, 5_000, 50);
+    let admin = admin_of(&env, &client);
+
+    let customer = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&customer, &1000);
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    client.complete_payment(&admin, &payment_id);
+
+    assert_eq!(
+        client.get_payment_settlement(&payment_id),
+        Some(Settlement {
+            token: usdc.clone(),
+            amount: 497
+        })
+    );
+    assert_eq!(credited(&client, &merchant, &usdc), 497);
+}
+
+#[test]
+fn test_swap_beyond_slippage_should_fail() {
+    let env = Env::default();
+    let (client, _merchant, token, _usdc, payment_id) = setup_swap(&env, 5_000, 200);
+    let admin = admin_of(&env, &client);
+
+    let result = client.try_complete_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::SlippageExceeded)));
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::Pending
+    );
+
+    // The swap is rolled back, so the held funds never left the contract
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&client.address),
+        1000
+    );
+}
+
+#[test]
+fn test_swap_bounded_by_oracle_not_router() {
+    let env = Env::default();
+
+    // The router fills at 1:0.4 with no impact, well below the oracle's 1:0.5
+    let (client, merchant, _token, usdc, payment_id) = setup_swap(&env, 4_000, 0);
+    let admin = admin_of(&env, &client);
+
+    let result = client.try_complete_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::SlippageExceeded)));
+    assert_eq!(credited(&client, &merchant, &usdc), 0);
+}
+
+#[test]
+fn test_batch_reports_failed_swap_per_item() {
+    let env = Env::default();
+    let (client, merchant, token, usdc, payment_id) = setup_swap(&env, 5_000, 200);
+    let admin = admin_of(&env, &client);
+
+    let customer = Address::generate(&env);
+    let other_merchant = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&customer, &500);
+    let other = client.create_payment(&customer, &other_merchant, &500_i128, &token);
+
+    // The swap beyond slippage fails on its own while the other payment completes
+    let ids = vec![&env, payment_id, other];
+    let results = client.batch_complete_payments(&admin, &ids);
+    assert_eq!(
+        error_codes(&env, &results),
+        vec![&env, Error::SlippageExceeded as u32, 0]
+    );
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::Pending
+    );
+    assert_eq!(client.get_payment(&other).status, PaymentStatus::Completed);
+    assert_eq!(credited(&client, &merchant, &usdc), 0);
+    assert_eq!(credited(&client, &other_merchant, &token), 500);
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&client.address),
+        1500
+    );
+}
+
+#[test]
+fn test_settlement_token_requires_router_and_valid_slippage() {
+    let env = Env::default();
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(&env, &contract_id);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let admin = Address::generate(&env);
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+    let token = create_token(&env, &customer, 1000);
+    let usdc = Address::generate(&env);
+
+    let result = client.try_set_settlement_token(
+        &merchant,
+        &Some(SettlementPreference {
+            token: usdc.clone(),
+            max_slippage_bps: 10_001,
+        }),
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidConfig)));
+
+    client.set_settlement_token(
+        &merchant,
+        &Some(SettlementPreference {
+            token: usdc,
+            max_slippage_bps: 100,
+        }),
+    );
+    let parts = vec![&env, (merchant.clone(), 1000_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+    let payment_id = client
+        .get_split_payment(&split_id)
+        .payment_ids
+        .get(0)
+        .unwrap();
+
+    let result = client.try_complete_payment(&admin, &payment_id);
+    assert_eq!(result, Err(Ok(Error::SwapRouterNotConfigured)));
+
+    // Opting out settles in the token the customer paid with again
+    client.set_settlement_token(&merchant, &None);
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(credited(&client, &merchant, &token), 1000);
+}
+
+// Ten cents per token at the oracle's 14 decimals
+const TEN_CENTS: i128 = 10_000_000_000_000;
+
+fn setup_oracle(
+    env: &Env,
+) -> (
+    PaymentContractClient<'_>,
+    MockOracleClient<'_>,
+    Address,
+    Address,
+    Address,
+) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+    let oracle_id = env.register(MockOracle, ());
+    let oracle = MockOracleClient::new(env, &oracle_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+
+    env.mock_all_auths();
+    env.ledger().set_timestamp(10_000);
+    let token = create_token(env, &customer, 10_000_000_000);
+    client.initialize(&Address::generate(env));
+    client.set_price_oracle(
+        &symbol_short!("USD"),
+        &Some(OracleConfig {
+            oracle: oracle_id,
+            max_age: 300,
+            max_deviation_bps: 500,
+        }),
+    );
+
+    (client, oracle, customer, merchant, token)
+}
+
+#[test]
+fn test_create_fiat_payment_prices_from_oracle() {
+    let env = Env::default();
+    let (client, oracle, customer, merchant, token) = setup_oracle(&env);
+    oracle.set_price(&token, &TEN_CENTS, &9_900);
+
+    // $25.00 at $0.10 per token is 250 tokens of 7 decimals
+    let payment_id =
+        client.create_fiat_payment(&customer, &merchant, &token, &2500, &symbol_short!("USD"));
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.amount, 2_500_000_000);
+    assert_eq!(payment.status, PaymentStatus::Pending);
+    assert_eq!(
+        payment.quote,
+        PriceQuote::Fiat(FiatQuote {
+            currency: symbol_short!("USD"),
+            fiat_amount: 2500,
+            price: TEN_CENTS,
+            decimals: 14,
+            priced_at: 9_900,
+        })
+    );
+
+    // A price that does not divide evenly rounds in the merchant's favour
+    oracle.set_price(&token, &(TEN_CENTS * 103 / 100), &9_950);
+    let payment_id =
+        client.create_fiat_payment(&customer, &merchant, &token, &1, &symbol_short!("USD"));
+    assert_eq!(client.get_payment(&payment_id).amount, 970_874);
+}
+
+#[test]
+fn test_fiat_payment_with_stale_price_should_fail() {
+    let env = Env::default();
+    let (client, oracle, customer, merchant, token) = setup_oracle(&env);
+    oracle.set_price(&token, &TEN_CENTS, &9_600);
+
+    let result =
+        client.try_create_fiat_payment(&customer, &merchant, &token, &2500, &symbol_short!("USD"));
+    assert_eq!(result, Err(Ok(Error::StalePrice)));
+
+    let result =
+        client.try_create_fiat_payment(&customer, &merchant, &token, &2500, &symbol_short!("EUR"));
+    assert_eq!(result, Err(Ok(Error::OracleNotConfigured)));
+
+    let other_token = Address::generate(&env);
+    let result = client.try_create_fiat_payment(
+        &customer,
+        &merchant,
+        &other_token,
+        &2500,
+        &symbol_short!("USD"),
+    );
+    assert_eq!(result, Err(Ok(Error::PriceUnavailable)));
+}
+
+#[test]
+fn test_fiat_payment_with_price_jump_should_fail() {
+    let env = Env::default();
+    let (client, oracle, customer, merchant, token) = setup_oracle(&env);
+    oracle.set_price(&token, &TEN_CENTS, &9_800);
+    oracle.set_price(&token, &(TEN_CENTS * 11 / 10), &9_900);
+
+    // A ten percent move is beyond the five percent guard
+    let result =
+        client.try_create_fiat_payment(&customer, &merchant, &token, &2500, &symbol_short!("USD"));
+    assert_eq!(result, Err(Ok(Error::PriceDeviationExceeded)));
+
+    oracle.set_price(&token, &(TEN_CENTS * 112 / 100), &9_950);
+    let payment_id =
+        client.create_fiat_payment(&customer, &merchant, &token, &2200, &symbol_short!("USD"));
+    assert_eq!(client.get_payment(&payment_id).amount, 1_964_285_715);
+}
+
+fn sign_intent(env: &Env, signing_key: &SigningKey, intent: &PaymentIntent) -> BytesN<64> {
+    let payload = intent.clone().to_xdr(env);
+    let mut buf = [0u8; 512];
+    let message = &mut buf[..payload.len() as usize];
+    payload.copy_into_slice(message);
+    BytesN::from_array(env, &signing_key.sign(message).to_bytes())
+}
+
+fn setup_intent(
+    env: &Env,
+) -> (
+    PaymentContractClient<'_>,
+    SigningKey,
+    Address,
+    PaymentIntent,
+) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+    let signing_key = SigningKey::from_bytes(&[7; 32]);
+
+    env.mock_all_auths();
+    env.ledger().set_timestamp(1_000);
+    let token = create_token(env, &customer, 1000);
+    client.set_signing_key(
+        &merchant,
+        &Some(BytesN::from_array(
+            env,
+            &signing_key.verifying_key().to_bytes(),
+        )),
+    );
+
+    let intent = PaymentIntent {
+        contract: contract_id,
+        merchant,
+        token,
+        amount: 750,
+        order_ref: String::from_str(env, "ORDER-42"),
+        nonce: 1,
+        expires_at: 2_000,
+    };
+
+    (client, signing_key, customer, intent)
+}
+
+#[test]
+fn test_create_payment_from_signed_intent() {
+    let env = Env::default();
+    let (client, signing_key, customer, intent) = setup_intent(&env);
+    let signature = sign_intent(&env, &signing_key, &intent);
+
+    let payment_id = client.create_payment_from_intent(&customer, &intent, &signature);
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.customer, customer);
+    assert_eq!(payment.merchant, intent.merchant);
+    assert_eq!(payment.token, intent.token);
+    assert_eq!(payment.amount, 750);
+    assert_eq!(payment.order_ref, Some(intent.order_ref.clone()));
+
+    // Each intent can only be used once
+    let result = client.try_create_payment_from_intent(&customer, &intent, &signature);
+    assert_eq!(result.err(), Some(Ok(Error::NonceUsed)));
+}
+
+#[test]
+fn test_used_nonce_lives_until_intent_expires() {
+    let env = Env::default();
+    let (client, signing_key, customer, mut intent) = setup_intent(&env);
+    intent.expires_at = 1_000 + 30 * 24 * 60 * 60;
+    let signature = sign_intent(&env, &signing_key, &intent);
+
+    client.create_payment_from_intent(&customer, &intent, &signature);
+
+    // Thirty days of five second ledgers, held outside instance storage
+    let key = DataKey::IntentNonce(intent.merchant.clone(), intent.nonce);
+    env.as_contract(&client.address, || {
+        assert!(!env.storage().instance().has(&key));
+        assert!(env.storage().persistent().get_ttl(&key) >= 518_400);
+    });
+}
+
+#[test]
+#[should_panic]
+fn test_tampered_intent_should_panic() {
+    let env = Env::default();
+    let (client, signing_key, customer, mut intent) = setup_intent(&env);
+    let signature = sign_intent(&env, &signing_key, &intent);
+
+    intent.amount = 1;
+    client.create_payment_from_intent(&customer, &intent, &signature);
+}
+
+#[test]
+fn test_invalid_intent_should_fail() {
+    let env = Env::default();
+    let (client, signing_key, customer, intent) = setup_intent(&env);
+
+    let mut expired = intent.clone();
+    expired.expires_at = 999;
+    let signature = sign_intent(&env, &signing_key, &expired);
+    let result = client.try_create_payment_from_intent(&customer, &expired, &signature);
+    assert_eq!(result.err(), Some(Ok(Error::IntentExpired)));
+
+    // Intents signed for another deployment are rejected
+    let mut foreign = intent.clone();
+    foreign.contract = Address::generate(&env);
+    let signature = sign_intent(&env, &signing_key, &foreign);
+    let result = client.try_create_payment_from_intent(&customer, &foreign, &signature);
+    assert_eq!(result.err(), Some(Ok(Error::InvalidIntent)));
+
+    client.set_signing_key(&intent.merchant, &None);
+    let signature = sign_intent(&env, &signing_key, &intent);
+    let result = client.try_create_payment_from_intent(&customer, &intent, &signature);
+    assert_eq!(result.err(), Some(Ok(Error::SigningKeyNotSet)));
+}
+
+fn setup_mandate(env: &Env) -> (PaymentContractClient<'_>, Address, Address, Address, u64) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+
+    env.mock_all_auths();
+    let token = create_token(env, &customer, 1000);
+    let mandate_id = client.create_mandate(&customer, &merchant, &token, &100, &250, &(3 * MONTH));
+
+    (client, customer, merchant, token, mandate_id)
+}
+
+#[test]
+fn test_charge_mandate_within_caps() {
+    let env = Env::default();
+    let (client, customer, merchant, token, mandate_id) = setup_mandate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    // Three periods of the period cap are approved up front
+    assert_eq!(token_client.allowance(&customer, &client.address), 750);
+
+    let payment_id = client.charge_mandate(&merchant, &mandate_id, &100);
+    client.charge_mandate(&merchant, &mandate_id, &100);
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.status, PaymentStatus::Completed);
+    assert_eq!(payment.mandate_id, Some(mandate_id));
+
+    // Over the per-payment cap, then over what is left of the period cap
+    let result = client.try_charge_mandate(&merchant, &mandate_id, &150);
+    assert_eq!(result, Err(Ok(Error::CapExceeded)));
+    let result = client.try_charge_mandate(&merchant, &mandate_id, &100);
+    assert_eq!(result, Err(Ok(Error::CapExceeded)));
+    client.charge_mandate(&merchant, &mandate_id, &50);
+
+    // A new period starts with a fresh period cap
+    env.ledger().set_timestamp(MONTH + 1);
+    client.charge_mandate(&merchant, &mandate_id, &100);
+    assert_eq!(client.get_mandate(&mandate_id).spent_in_period, 100);
+
+    assert_eq!(token_client.balance(&customer), 650);
+    assert_eq!(credited(&client, &merchant, &token), 350);
+}
+
+#[test]
+fn test_subscription_keeps_existing_mandate_allowance() {
+    let env = Env::default();
+    let (client, customer, merchant, token, mandate_id) = setup_mandate(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    // The subscription's allowance is added to the mandate's rather than replacing it
+    let subscription_id =
+        client.create_subscription(&customer, &merchant, &token, &100_i128, &MONTH, &3);
+    assert_eq!(token_client.allowance(&customer, &client.address), 1050);
+
+    client.charge_subscription(&subscription_id);
+    client.charge_mandate(&merchant, &mandate_id, &100);
+
+    assert_eq!(token_client.allowance(&customer, &client.address), 850);
+    assert_eq!(credited(&client, &merchant, &token), 200);
+}
+
+#[test]
+fn test_revoked_or_expired_mandate_should_fail() {
+    let env = Env::default();
+    let (client, customer, merchant, token, mandate_id) = setup_mandate(&env);
+
+    let result = client.try_charge_mandate(&Address::generate(&env), &mandate_id, &50);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    client.revoke_mandate(&customer, &mandate_id);
+    assert_eq!(
+        client.get_mandate(&mandate_id).status,
+        MandateStatus::Revoked
+    );
+    let result = client.try_charge_mandate(&merchant, &mandate_id, &50);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+
+    let mandate_id = client.create_mandate(&customer, &merchant, &token, &100, &250, &MONTH);
+    env.ledger().set_timestamp(MONTH);
+    let result = client.try_charge_mandate(&merchant, &mandate_id, &50);
+    assert_eq!(result, Err(Ok(Error::MandateExpired)));
+
+    // The per-payment cap cannot exceed the period cap
+    let result = client.try_create_mandate(&customer, &merchant, &token, &300, &250, &(2 * MONTH));
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+fn setup_stream(env: &Env) -> (PaymentContractClient<'_>, Address, Address, Address, u64) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+
+    env.mock_all_auths();
+    let token = create_token(env, &customer, 1000);
+    let stream_id = client.create_stream(&customer, &merchant, &token, &1000, &100, &1100);
+
+    (client, customer, merchant, token, stream_id)
+}
+
+#[test]
+fn test_stream_vests_linearly() {
+    let env = Env::default();
+    let (client, _customer, merchant, token, stream_id) = setup_stream(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    // Nothing vests before the start
+    assert_eq!(client.streamed_amount(&stream_id), 0);
+    let result = client.try_withdraw_from_stream(&merchant, &stream_id);
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+
+    env.ledger().set_timestamp(600);
+    assert_eq!(client.streamed_amount(&stream_id), 500);
+    assert_eq!(client.withdraw_from_stream(&merchant, &stream_id), 500);
+    assert_eq!(token_client.balance(&merchant), 500);
+
+    env.ledger().set_timestamp(2_000);
+    assert_eq!(client.streamed_amount(&stream_id), 1000);
+    assert_eq!(client.withdraw_from_stream(&merchant, &stream_id), 500);
+    assert_eq!(token_client.balance(&merchant), 1000);
+    assert_eq!(
+        client.get_stream(&stream_id).status,
+        StreamStatus::Completed
+    );
+
+    let result = client.try_withdraw_from_stream(&merchant, &stream_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_cancel_stream_settles_both_sides() {
+    let env = Env::default();
+    let (client, customer, merchant, token, stream_id) = setup_stream(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    env.ledger().set_timestamp(350);
+    assert_eq!(client.withdraw_from_stream(&merchant, &stream_id), 250);
+
+    let result = client.try_cancel_stream(&Address::generate(&env), &stream_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+
+    // The merchant gets the rest of what vested, the customer the unvested part
+    env.ledger().set_timestamp(600);
+    client.cancel_stream(&customer, &stream_id);
+    assert_eq!(token_client.balance(&merchant), 500);
+    assert_eq!(token_client.balance(&customer), 500);
+    assert_eq!(token_client.balance(&client.address), 0);
+
+    // Vesting stops at cancellation
+    env.ledger().set_timestamp(900);
+    assert_eq!(client.streamed_amount(&stream_id), 500);
+    let result = client.try_cancel_stream(&merchant, &stream_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_create_stream_with_invalid_schedule_should_fail() {
+    let env = Env::default();
+    let (client, customer, merchant, token, _stream_id) = setup_stream(&env);
+
+    let result = client.try_create_stream(&customer, &merchant, &token, &0, &100, &1100);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+    let result = client.try_create_stream(&customer, &merchant, &token, &1000, &1100, &1100);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+    assert_eq!(client.try_get_stream(&99), Err(Ok(Error::StreamNotFound)));
+}
+
+// The tip reported by the last PaymentCompleted event
+fn completed_tip(env: &Env) -> i128 {
+    let name = Symbol::new(env, "payment_completed");
+    let (_, _, data) = env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, topics, _)| {
+            Symbol::try_from_val(env, &topics.get(0).unwrap()).ok() == Some(name.clone())
+        })
+        .unwrap();
+    let data = Map::<Symbol, Val>::try_from_val(env, &data).unwrap();
+    i128::try_from_val(env, &data.get(Symbol::new(env, "tip")).unwrap()).unwrap()
+}
+
+fn setup_tip(env: &Env) -> (PaymentContractClient<'_>, Address, Address, Address) {
+    let contract_id = env.register(PaymentContract, ());
+    let client = PaymentContractClient::new(env, &contract_id);
+
+    let customer = Address::generate(env);
+    let merchant = Address::generate(env);
+
+    env.mock_all_auths();
+    let token = create_token(env, &customer, 1000);
+    client.initialize(&Address::generate(env));
+
+    (client, customer, merchant, token)
+}
+
+#[test]
+fn test_tip_paid_out_on_completion() {
+    let env = Env::default();
+    let (client, customer, merchant, token) = setup_tip(&env);
+    let admin = admin_of(&env, &client);
+    let token_client = token::Client::new(&env, &token);
+
+    let payment_id = client.create_payment_with_tip(&customer, &merchant, &500, &token, &50, &None);
+    assert_eq!(token_client.balance(&client.address), 550);
+
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(completed_tip(&env), 50);
+
+    // Without a tip recipient the merchant receives the tip; the amount goes to its balance
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.tip_amount, 50);
+    assert_eq!(payment.fee, 0);
+    assert_eq!(token_client.balance(&merchant), 50);
+    assert_eq!(credited(&client, &merchant, &token), 500);
+    assert_eq!(token_client.balance(&client.address), 500);
+
+    let result = client.try_create_payment_with_tip(&customer, &merchant, &500, &token, &0, &None);
+    assert_eq!(result, Err(Ok(Error::InvalidAmount)));
+}
+
+#[test]
+fn test_tip_paid_once_after_failed_completion() {
+    let env = Env::default();
+    let (client, customer, merchant, token) = setup_tip(&env);
+    let admin = admin_of(&env, &client);
+    let token_client = token::Client::new(&env, &token);
+    let escrow_id = env.register(escrow::EscrowContract, ());
+    escrow::EscrowContractClient::new(&env, &escrow_id).initialize(&Address::generate(&env));
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+
+    let payment_id = client.create_payment_with_tip(&customer, &merchant, &500, &token, &50, &None);
+
+    // Without an escrow contract the item fails and the tip stays with this contract
+    let ids = vec![&env, payment_id];
+    let results = client.batch_complete_payments(&admin, &ids);
+    assert!(!results.get(0).unwrap().success);
+    assert_eq!(token_client.balance(&merchant), 0);
+    assert_eq!(token_client.balance(&client.address), 550);
+
+    // The retry pays the tip exactly once and reports it alongside the escrow hand-off
+    client.set_escrow_contract(&escrow_id);
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(completed_tip(&env), 50);
+    assert_eq!(token_client.balance(&merchant), 50);
+    assert_eq!(token_client.balance(&escrow_id), 500);
+    assert_eq!(
+        client.get_payment(&payment_id).status,
+        PaymentStatus::InEscrow
+    );
+}
+
+#[test]
+fn test_platform_fee_taken_before_escrow() {
+    let env = Env::default();
+    let (client, escrow_client) = setup_escrow_backed(&env);
+    let admin = admin_of(&env, &client);
+
+    let customer = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let platform = Address::generate(&env);
+    let token = create_token(&env, &customer, 1000);
+    client.set_fee_config(&Some(FeeConfig {
+        recipient: platform.clone(),
+        fee_bps: 1000,
+        exempt_tips: true,
+    }));
+
+    client.set_dispute_window(&merchant, &Some(DISPUTE_WINDOW));
+    let payment_id = client.create_payment(&customer, &merchant, &1000_i128, &token);
+    client.complete_payment(&admin, &payment_id);
+
+    let payment = client.get_payment(&payment_id);
+    assert_eq!(payment.fee, 100);
+    assert_eq!(credited(&client, &platform, &token), 100);
+    let escrow = escrow_client.get_escrow(&payment.escrow_id.unwrap());
+    assert_eq!(escrow.amount, 900);
+    assert_eq!(
+        token::Client::new(&env, &token).balance(&escrow_client.address),
+        900
+    );
+}
+
+#[test]
+fn test_cancel_payment_returns_tip() {
+    let env = Env::default();
+    let (client, customer, merchant, token) = setup_tip(&env);
+    let token_client = token::Client::new(&env, &token);
+
+    let payment_id = client.create_payment_with_tip(&customer, &merchant, &500, &token, &50, &None);
+    client.cancel_payment(&customer, &payment_id);
+
+    assert_eq!(token_client.balance(&customer), 1000);
+    assert_eq!(token_client.balance(&client.address), 0);
+}
+
+#[test]
+fn test_platform_fee_excludes_tips_when_configured() {
+    let env = Env::default();
+    let (client, customer, merchant, token) = setup_tip(&env);
+    let admin = admin_of(&env, &client);
+    let token_client = token::Client::new(&env, &token);
+    let platform = Address::generate(&env);
+    let waiter = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &token).mint(&customer, &1000);
+
+    let mut config = FeeConfig {
+        recipient: platform.clone(),
+        fee_bps: 1000,
+        exempt_tips: false,
+    };
+    client.set_fee_config(&Some(config.clone()));
+
+    // The amount and the tip both pay the ten percent fee
+    let parts = vec![&env, (merchant.clone(), 500_i128)];
+    let split_id = client.create_split_payment(&customer, &token, &parts);
+    let payment_id = client
+        .get_split_payment(&split_id)
+        .payment_ids
+        .get(0)
+        .unwrap();
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(credited(&client, &merchant, &token), 450);
+
+    let payment_id = client.create_payment_with_tip(
+        &customer,
+        &merchant,
+        &200,
+        &token,
+        &100,
+        &Some(waiter.clone()),
+    );
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(token_client.balance(&waiter), 90);
+    assert_eq!(client.get_payment(&payment_id).fee, 30);
+    assert_eq!(credited(&client, &platform, &token), 80);
+    assert_eq!(credited(&client, &merchant, &token), 630);
+
+    // Exempt tips reach the recipient in full while the amount still pays the fee
+    config.exempt_tips = true;
+    client.set_fee_config(&Some(config));
+    let payment_id = client.create_payment_with_tip(
+        &customer,
+        &merchant,
+        &200,
+        &token,
+        &100,
+        &Some(waiter.clone()),
+    );
+    client.complete_payment(&admin, &payment_id);
+    assert_eq!(token_client.balance(&waiter), 190);
+    assert_eq!(client.get_payment(&payment_id).fee, 20);
+    assert_eq!(credited(&client, &platform, &token), 100);
+    assert_eq!(credited(&client, &merchant, &token), 810);
+}
@@ -1,11 +1,38 @@
 #![no_std]
-use soroban_sdk::{contract, contracterror, contractevent, contractimpl, contracttype, Address, Env, String};
+use soroban_sdk::{
+    contract, contracterror, contractevent, contractimpl, contracttype, token, Address, Env,
+    String, Vec,
+};
+
+const MAX_PAGE_SIZE: u32 = 50;
+const MAX_BATCH_SIZE: u32 = 50;
 
 #[derive(Clone)]
 #[contracttype]
 pub enum DataKey {
+    Admin,
     Refund(u64),
     RefundCounter,
+    Reserve(Address, Address),
+    ReserveCommitted(Address, Address),
+    ReserveThreshold(Address, Address),
+    PaymentRefundTotal(u64),
+    DestinationOverride(u64),
+    IndexLen(RefundIndex),
+    IndexEntry(RefundIndex, u64),
+    StatusCount(RefundStatus),
+    MerchantStatusCount(Address, RefundStatus),
+    RefundHistory(u64),
+}
+
+// Each index entry lives under its own persistent key so an index can grow without
+// bloating instance storage
+#[derive(Clone)]
+#[contracttype]
+pub enum RefundIndex {
+    Payment(u64),
+    Merchant(Address),
+    Customer(Address),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -15,6 +42,7 @@ pub enum RefundStatus {
     Approved,
     Rejected,
     Processed,
+    Cancelled,
 }
 
 #[contracterror]
@@ -26,6 +54,11 @@ pub enum Error {
     InvalidPaymentId = 4,
     InvalidStatus = 5,
     AlreadyProcessed = 6,
+    InsufficientReserve = 7,
+    JustificationRequired = 8,
+    BatchTooLarge = 9,
+    NotInitialized = 10,
+    AlreadyInitialized = 11,
 }
 
 #[contractevent]
@@ -56,6 +89,62 @@ pub struct RefundRejected {
     pub rejection_reason: String,
 }
 
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RefundCancelled {
+    pub refund_id: u64,
+    pub cancelled_by: Address,
+    pub cancelled_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RefundProcessed {
+    pub refund_id: u64,
+    pub processed_by: Address,
+    pub customer: Address,
+    pub recipient: Address,
+    pub amount: i128,
+    pub token: Address,
+    pub processed_at: u64,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RefundDestinationUpdated {
+    pub refund_id: u64,
+    pub destination: Address,
+    pub updated_by: Address,
+    pub justification: String,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReserveDeposited {
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReserveWithdrawn {
+    pub merchant: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub balance: i128,
+}
+
+#[contractevent]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReserveLowBalance {
+    pub merchant: Address,
+    pub token: Address,
+    pub balance: i128,
+    pub threshold: i128,
+}
+
 #[derive(Clone)]
 #[contracttype]
 pub struct Refund {
@@ -67,7 +156,38 @@ pub struct Refund {
     pub token: Address,
     pub status: RefundStatus,
     pub requested_at: u64,
+    pub approved_at: Option<u64>,
+    pub rejected_at: Option<u64>,
+    pub processed_at: Option<u64>,
     pub reason: String,
+    pub destination: Option<Address>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct RefundTransition {
+    pub status: RefundStatus,
+    pub actor: Address,
+    pub timestamp: u64,
+    pub sequence: u32,
+    pub note: String,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct DestinationOverride {
+    pub admin: Address,
+    pub destination: Address,
+    pub justification: String,
+    pub overridden_at: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct BatchResult {
+    pub id: u64,
+    pub success: bool,
+    pub error_code: u32,
 }
 
 #[contract]
@@ -75,6 +195,24 @@ pub struct RefundContract;
 
 #[contractimpl]
 impl RefundContract {
+    pub fn initialize(env: Env, admin: Address) -> Result<(), Error> {
+        if env.storage().instance().has(&DataKey::Admin) {
+            return Err(Error::AlreadyInitialized);
+        }
+
+        admin.require_auth();
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        Ok(())
+    }
+
+    pub fn get_admin(env: Env) -> Result<Address, Error> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Admin)
+            .ok_or(Error::NotInitialized)
+    }
+
     pub fn request_refund(
         env: Env,
         merchant: Address,
@@ -115,7 +253,11 @@ impl RefundContract {
             token: token.clone(),
             status: RefundStatus::Requested,
             requested_at: env.ledger().timestamp(),
-            reason,
+            approved_at: None,
+            rejected_at: None,
+            processed_at: None,
+            reason: reason.clone(),
+            destination: None,
         };
 
         // Store refund in contract storage
@@ -126,6 +268,17 @@ impl RefundContract {
             .instance()
             .set(&DataKey::RefundCounter, &refund_id);
 
+        // Commit the amount against the merchant's reserve and the payment
+        adjust_committed(&env, &merchant, &token, amount);
+        adjust_payment_refund_total(&env, payment_id, amount);
+
+        // Index the refund for the listing views
+        push_index(&env, RefundIndex::Payment(payment_id), refund_id);
+        push_index(&env, RefundIndex::Merchant(merchant.clone()), refund_id);
+        push_index(&env, RefundIndex::Customer(customer.clone()), refund_id);
+        adjust_status_count(&env, &merchant, RefundStatus::Requested, 1);
+        record_transition(&env, refund_id, RefundStatus::Requested, &merchant, reason);
+
         // Emit RefundRequested event
         RefundRequested {
             refund_id,
@@ -151,7 +304,46 @@ impl RefundContract {
 
     pub fn approve_refund(env: Env, admin: Address, refund_id: u64) -> Result<(), Error> {
         // Require admin authentication
-        admin.require_auth();
+        check_admin(&env, &admin)?;
+
+        approve(&env, &admin, refund_id)
+    }
+
+    pub fn batch_approve_refunds(
+        env: Env,
+        admin: Address,
+        refund_ids: Vec<u64>,
+    ) -> Result<Vec<BatchResult>, Error> {
+        // Require admin authentication once for the whole batch
+        check_admin(&env, &admin)?;
+
+        if refund_ids.len() > MAX_BATCH_SIZE {
+            return Err(Error::BatchTooLarge);
+        }
+
+        // A failing id is reported in its result instead of reverting the batch; approve
+        // returns its errors before writing anything, so a failed id leaves no trace
+        let mut results = Vec::new(&env);
+        for refund_id in refund_ids.iter() {
+            let outcome = approve(&env, &admin, refund_id);
+            results.push_back(BatchResult {
+                id: refund_id,
+                success: outcome.is_ok(),
+                error_code: outcome.err().map_or(0, |e| e as u32),
+            });
+        }
+
+        Ok(results)
+    }
+
+    pub fn reject_refund(
+        env: Env,
+        admin: Address,
+        refund_id: u64,
+        rejection_reason: String,
+    ) -> Result<(), Error> {
+        // Require admin authentication
+        check_admin(&env, &admin)?;
 
         // Retrieve refund from storage
         let mut refund: Refund = env
@@ -165,33 +357,39 @@ impl RefundContract {
             return Err(Error::InvalidStatus);
         }
 
-        // Update refund status to Approved
-        refund.status = RefundStatus::Approved;
+        // Update refund status to Rejected
+        set_status(
+            &env,
+            &mut refund,
+            RefundStatus::Rejected,
+            &admin,
+            rejection_reason.clone(),
+        );
 
         // Store updated refund back to storage
         env.storage()
             .instance()
             .set(&DataKey::Refund(refund_id), &refund);
 
-        // Emit RefundApproved event
-        RefundApproved {
+        // Release the commitments held against the merchant's reserve and the payment
+        adjust_committed(&env, &refund.merchant, &refund.token, -refund.amount);
+        adjust_payment_refund_total(&env, refund.payment_id, -refund.amount);
+
+        // Emit RefundRejected event
+        RefundRejected {
             refund_id,
-            approved_by: admin,
-            approved_at: env.ledger().timestamp(),
+            rejected_by: admin,
+            rejected_at: env.ledger().timestamp(),
+            rejection_reason,
         }
         .publish(&env);
 
         Ok(())
     }
 
-    pub fn reject_refund(
-        env: Env,
-        admin: Address,
-        refund_id: u64,
-        rejection_reason: String,
-    ) -> Result<(), Error> {
-        // Require admin authentication
-        admin.require_auth();
+    pub fn cancel_refund(env: Env, merchant: Address, refund_id: u64) -> Result<(), Error> {
+        // Require merchant authentication
+        merchant.require_auth();
 
         // Retrieve refund from storage
         let mut refund: Refund = env
@@ -200,30 +398,601 @@ impl RefundContract {
             .get(&DataKey::Refund(refund_id))
             .ok_or(Error::RefundNotFound)?;
 
+        // Only the merchant that requested the refund can withdraw it
+        if refund.merchant != merchant {
+            return Err(Error::Unauthorized);
+        }
+
         // Check refund status is Requested
         if refund.status != RefundStatus::Requested {
             return Err(Error::InvalidStatus);
         }
 
-        // Update refund status to Rejected
-        refund.status = RefundStatus::Rejected;
+        // Update refund status to Cancelled
+        set_status(
+            &env,
+            &mut refund,
+            RefundStatus::Cancelled,
+            &merchant,
+            String::from_str(&env, ""),
+        );
 
         // Store updated refund back to storage
         env.storage()
             .instance()
             .set(&DataKey::Refund(refund_id), &refund);
 
-        // Emit RefundRejected event
-        RefundRejected {
+        // Release the commitments held against the merchant's reserve and the payment
+        adjust_committed(&env, &refund.merchant, &refund.token, -refund.amount);
+        adjust_payment_refund_total(&env, refund.payment_id, -refund.amount);
+
+        // Emit RefundCancelled event
+        RefundCancelled {
             refund_id,
-            rejected_by: admin,
-            rejected_at: env.ledger().timestamp(),
-            rejection_reason,
+            cancelled_by: merchant,
+            cancelled_at: env.ledger().timestamp(),
         }
         .publish(&env);
 
         Ok(())
     }
+
+    pub fn update_refund_destination(
+        env: Env,
+        customer: Address,
+        refund_id: u64,
+        destination: Address,
+    ) -> Result<(), Error> {
+        // Require customer authentication
+        customer.require_auth();
+
+        let refund: Refund = env
+            .storage()
+            .instance()
+            .get(&DataKey::Refund(refund_id))
+            .ok_or(Error::RefundNotFound)?;
+
+        // Only the refunded customer can redirect their own refund
+        if refund.customer != customer {
+            return Err(Error::Unauthorized);
+        }
+
+        set_destination(
+            &env,
+            refund,
+            destination,
+            customer,
+            String::from_str(&env, ""),
+        )?;
+
+        // The customer's own choice supersedes any earlier admin override
+        env.storage()
+            .instance()
+            .remove(&DataKey::DestinationOverride(refund_id));
+
+        Ok(())
+    }
+
+    pub fn override_refund_destination(
+        env: Env,
+        admin: Address,
+        refund_id: u64,
+        destination: Address,
+        justification: String,
+    ) -> Result<(), Error> {
+        // Require admin authentication
+        check_admin(&env, &admin)?;
+
+        // An admin override must always be explained
+        if justification.is_empty() {
+            return Err(Error::JustificationRequired);
+        }
+
+        let refund: Refund = env
+            .storage()
+            .instance()
+            .get(&DataKey::Refund(refund_id))
+            .ok_or(Error::RefundNotFound)?;
+
+        set_destination(
+            &env,
+            refund,
+            destination.clone(),
+            admin.clone(),
+            justification.clone(),
+        )?;
+
+        // Keep the justification alongside the refund for later review
+        env.storage().instance().set(
+            &DataKey::DestinationOverride(refund_id),
+            &DestinationOverride {
+                admin,
+                destination,
+                justification,
+                overridden_at: env.ledger().timestamp(),
+            },
+        );
+
+        Ok(())
+    }
+
+    pub fn get_destination_override(env: Env, refund_id: u64) -> Option<DestinationOverride> {
+        env.storage()
+            .instance()
+            .get(&DataKey::DestinationOverride(refund_id))
+    }
+
+    pub fn process_refund(env: Env, admin: Address, refund_id: u64) -> Result<(), Error> {
+        // Require admin authentication
+        check_admin(&env, &admin)?;
+
+        // Retrieve refund from storage
+        let mut refund: Refund = env
+            .storage()
+            .instance()
+            .get(&DataKey::Refund(refund_id))
+            .ok_or(Error::RefundNotFound)?;
+
+        // Only approved refunds can be paid out
+        match refund.status {
+            RefundStatus::Approved => {}
+            RefundStatus::Processed => return Err(Error::AlreadyProcessed),
+            _ => return Err(Error::InvalidStatus),
+        }
+
+        // Debit the merchant's reserve
+        let balance = get_reserve_balance(&env, &refund.merchant, &refund.token);
+        if balance < refund.amount {
+            return Err(Error::InsufficientReserve);
+        }
+        let new_balance = balance - refund.amount;
+        set_reserve_balance(&env, &refund.merchant, &refund.token, new_balance);
+        adjust_committed(&env, &refund.merchant, &refund.token, -refund.amount);
+
+        // Update refund status to Processed
+        set_status(
+            &env,
+            &mut refund,
+            RefundStatus::Processed,
+            &admin,
+            String::from_str(&env, ""),
+        );
+        env.storage()
+            .instance()
+            .set(&DataKey::Refund(refund_id), &refund);
+
+        // Pay the customer, or their chosen destination, out of the reserve
+        let recipient = refund
+            .destination
+            .clone()
+            .unwrap_or(refund.customer.clone());
+        token::Client::new(&env, &refund.token).transfer(
+            &env.current_contract_address(),
+            &recipient,
+            &refund.amount,
+        );
+
+        // Emit RefundProcessed event
+        RefundProcessed {
+            refund_id,
+            processed_by: admin,
+            customer: refund.customer.clone(),
+            recipient,
+            amount: refund.amount,
+            token: refund.token.clone(),
+            processed_at: env.ledger().timestamp(),
+        }
+        .publish(&env);
+
+        check_low_balance(&env, &refund.merchant, &refund.token, new_balance);
+
+        Ok(())
+    }
+
+    pub fn deposit_reserve(
+        env: Env,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        // Require merchant authentication
+        merchant.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Move the funds into the contract
+        token::Client::new(&env, &token).transfer(
+            &merchant,
+            env.current_contract_address(),
+            &amount,
+        );
+
+        let balance = get_reserve_balance(&env, &merchant, &token) + amount;
+        set_reserve_balance(&env, &merchant, &token, balance);
+
+        ReserveDeposited {
+            merchant,
+            token,
+            amount,
+            balance,
+        }
+        .publish(&env);
+
+        Ok(balance)
+    }
+
+    pub fn withdraw_reserve(
+        env: Env,
+        merchant: Address,
+        token: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        // Require merchant authentication
+        merchant.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        // Only the part of the reserve not committed to pending refunds is free
+        let balance = get_reserve_balance(&env, &merchant, &token);
+        let committed = get_committed(&env, &merchant, &token);
+        if balance - committed < amount {
+            return Err(Error::InsufficientReserve);
+        }
+
+        let new_balance = balance - amount;
+        set_reserve_balance(&env, &merchant, &token, new_balance);
+
+        token::Client::new(&env, &token).transfer(
+            &env.current_contract_address(),
+            &merchant,
+            &amount,
+        );
+
+        ReserveWithdrawn {
+            merchant: merchant.clone(),
+            token: token.clone(),
+            amount,
+            balance: new_balance,
+        }
+        .publish(&env);
+
+        check_low_balance(&env, &merchant, &token, new_balance);
+
+        Ok(new_balance)
+    }
+
+    pub fn set_reserve_threshold(
+        env: Env,
+        merchant: Address,
+        token: Address,
+        threshold: i128,
+    ) -> Result<(), Error> {
+        // Require merchant authentication
+        merchant.require_auth();
+
+        if threshold < 0 {
+            return Err(Error::InvalidAmount);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ReserveThreshold(merchant, token), &threshold);
+
+        Ok(())
+    }
+
+    pub fn get_reserve(env: Env, merchant: Address, token: Address) -> i128 {
+        get_reserve_balance(&env, &merchant, &token)
+    }
+
+    pub fn get_reserve_committed(env: Env, merchant: Address, token: Address) -> i128 {
+        get_committed(&env, &merchant, &token)
+    }
+
+    pub fn get_refund_history(env: Env, refund_id: u64) -> Result<Vec<RefundTransition>, Error> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::RefundHistory(refund_id))
+            .ok_or(Error::RefundNotFound)
+    }
+
+    pub fn list_refunds_by_payment(
+        env: Env,
+        payment_id: u64,
+        cursor: u64,
+        limit: u32,
+    ) -> Vec<Refund> {
+        load_refunds(
+            &env,
+            RefundIndex::Payment(payment_id),
+            None,
+            cursor,
+            limit.min(MAX_PAGE_SIZE),
+        )
+    }
+
+    pub fn list_refunds_by_merchant(
+        env: Env,
+        merchant: Address,
+        status: Option<RefundStatus>,
+        cursor: u64,
+        limit: u32,
+    ) -> Vec<Refund> {
+        load_refunds(
+            &env,
+            RefundIndex::Merchant(merchant),
+            status,
+            cursor,
+            limit.min(MAX_PAGE_SIZE),
+        )
+    }
+
+    pub fn list_refunds_by_customer(
+        env: Env,
+        customer: Address,
+        cursor: u64,
+        limit: u32,
+    ) -> Vec<Refund> {
+        load_refunds(
+            &env,
+            RefundIndex::Customer(customer),
+            None,
+            cursor,
+            limit.min(MAX_PAGE_SIZE),
+        )
+    }
+
+    pub fn get_refund_count(env: Env, status: RefundStatus) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::StatusCount(status))
+            .unwrap_or(0)
+    }
+
+    pub fn get_merchant_refund_count(env: Env, merchant: Address, status: RefundStatus) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MerchantStatusCount(merchant, status))
+            .unwrap_or(0)
+    }
+
+    pub fn get_payment_refund_total(env: Env, payment_id: u64) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::PaymentRefundTotal(payment_id))
+            .unwrap_or(0)
+    }
+}
+
+fn check_admin(env: &Env, admin: &Address) -> Result<(), Error> {
+    admin.require_auth();
+
+    // Signing is not enough; the caller must be the admin set at initialize
+    if admin != &RefundContract::get_admin(env.clone())? {
+        return Err(Error::Unauthorized);
+    }
+    Ok(())
+}
+
+fn approve(env: &Env, admin: &Address, refund_id: u64) -> Result<(), Error> {
+    // Retrieve refund from storage
+    let mut refund: Refund = env
+        .storage()
+        .instance()
+        .get(&DataKey::Refund(refund_id))
+        .ok_or(Error::RefundNotFound)?;
+
+    // Check refund status is Requested
+    if refund.status != RefundStatus::Requested {
+        return Err(Error::InvalidStatus);
+    }
+
+    // Update refund status to Approved
+    set_status(
+        env,
+        &mut refund,
+        RefundStatus::Approved,
+        admin,
+        String::from_str(env, ""),
+    );
+
+    // Store updated refund back to storage
+    env.storage()
+        .instance()
+        .set(&DataKey::Refund(refund_id), &refund);
+
+    // Emit RefundApproved event
+    RefundApproved {
+        refund_id,
+        approved_by: admin.clone(),
+        approved_at: env.ledger().timestamp(),
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn set_status(env: &Env, refund: &mut Refund, status: RefundStatus, actor: &Address, note: String) {
+    // Keep the per-status counters in step with every transition
+    adjust_status_count(env, &refund.merchant, refund.status.clone(), -1);
+    adjust_status_count(env, &refund.merchant, status.clone(), 1);
+
+    let now = env.ledger().timestamp();
+    match status {
+        RefundStatus::Approved => refund.approved_at = Some(now),
+        RefundStatus::Rejected => refund.rejected_at = Some(now),
+        RefundStatus::Processed => refund.processed_at = Some(now),
+        _ => {}
+    }
+
+    record_transition(env, refund.id, status.clone(), actor, note);
+    refund.status = status;
+}
+
+fn record_transition(
+    env: &Env,
+    refund_id: u64,
+    status: RefundStatus,
+    actor: &Address,
+    note: String,
+) {
+    // Each refund has its own persistent history entry, loaded only when that refund changes
+    let key = DataKey::RefundHistory(refund_id);
+    let mut history: Vec<RefundTransition> = env
+        .storage()
+        .persistent()
+        .get(&key)
+        .unwrap_or(Vec::new(env));
+    history.push_back(RefundTransition {
+        status,
+        actor: actor.clone(),
+        timestamp: env.ledger().timestamp(),
+        sequence: env.ledger().sequence(),
+        note,
+    });
+    env.storage().persistent().set(&key, &history);
+}
+
+fn adjust_status_count(env: &Env, merchant: &Address, status: RefundStatus, delta: i64) {
+    let storage = env.storage().instance();
+
+    let global_key = DataKey::StatusCount(status.clone());
+    let global: u64 = storage.get(&global_key).unwrap_or(0);
+    storage.set(&global_key, &global.saturating_add_signed(delta));
+
+    let merchant_key = DataKey::MerchantStatusCount(merchant.clone(), status);
+    let count: u64 = storage.get(&merchant_key).unwrap_or(0);
+    storage.set(&merchant_key, &count.saturating_add_signed(delta));
+}
+
+fn push_index(env: &Env, index: RefundIndex, refund_id: u64) {
+    let storage = env.storage().persistent();
+    let len_key = DataKey::IndexLen(index.clone());
+    let len: u64 = storage.get(&len_key).unwrap_or(0);
+    storage.set(&DataKey::IndexEntry(index, len), &refund_id);
+    storage.set(&len_key, &(len + 1));
+}
+
+fn load_refunds(
+    env: &Env,
+    index: RefundIndex,
+    status: Option<RefundStatus>,
+    cursor: u64,
+    limit: u32,
+) -> Vec<Refund> {
+    // The cursor is the number of index entries already read, and a page reads at most
+    // limit entries; with a status filter a page can hold fewer refunds than that, so
+    // callers advance the cursor by limit rather than by the page length
+    let storage = env.storage().persistent();
+    let len: u64 = storage.get(&DataKey::IndexLen(index.clone())).unwrap_or(0);
+    let end = len.min(cursor.saturating_add(limit as u64));
+    let mut refunds = Vec::new(env);
+    for position in cursor..end {
+        let refund_id: u64 = storage
+            .get(&DataKey::IndexEntry(index.clone(), position))
+            .unwrap();
+        let refund: Refund = env
+            .storage()
+            .instance()
+            .get(&DataKey::Refund(refund_id))
+            .unwrap();
+        if status.as_ref().is_none_or(|s| *s == refund.status) {
+            refunds.push_back(refund);
+        }
+    }
+    refunds
+}
+
+fn set_destination(
+    env: &Env,
+    mut refund: Refund,
+    destination: Address,
+    updated_by: Address,
+    justification: String,
+) -> Result<(), Error> {
+    // The payout address is locked once the refund is settled
+    match refund.status {
+        RefundStatus::Requested | RefundStatus::Approved => {}
+        RefundStatus::Processed => return Err(Error::AlreadyProcessed),
+        _ => return Err(Error::InvalidStatus),
+    }
+
+    // Pointing the refund back at the customer clears the alternate destination
+    refund.destination = if destination == refund.customer {
+        None
+    } else {
+        Some(destination.clone())
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::Refund(refund.id), &refund);
+
+    RefundDestinationUpdated {
+        refund_id: refund.id,
+        destination,
+        updated_by,
+        justification,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+fn get_reserve_balance(env: &Env, merchant: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::Reserve(merchant.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+fn set_reserve_balance(env: &Env, merchant: &Address, token: &Address, balance: i128) {
+    env.storage()
+        .instance()
+        .set(&DataKey::Reserve(merchant.clone(), token.clone()), &balance);
+}
+
+fn get_committed(env: &Env, merchant: &Address, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::ReserveCommitted(merchant.clone(), token.clone()))
+        .unwrap_or(0)
+}
+
+fn adjust_committed(env: &Env, merchant: &Address, token: &Address, delta: i128) {
+    let committed = get_committed(env, merchant, token) + delta;
+    env.storage().instance().set(
+        &DataKey::ReserveCommitted(merchant.clone(), token.clone()),
+        &committed,
+    );
+}
+
+fn adjust_payment_refund_total(env: &Env, payment_id: u64, delta: i128) {
+    let total = RefundContract::get_payment_refund_total(env.clone(), payment_id) + delta;
+    env.storage()
+        .instance()
+        .set(&DataKey::PaymentRefundTotal(payment_id), &total);
+}
+
+fn check_low_balance(env: &Env, merchant: &Address, token: &Address, balance: i128) {
+    // Notify the merchant once the reserve drops under their configured threshold
+    let threshold: i128 = env
+        .storage()
+        .instance()
+        .get(&DataKey::ReserveThreshold(merchant.clone(), token.clone()))
+        .unwrap_or(0);
+    if balance < threshold {
+        ReserveLowBalance {
+            merchant: merchant.clone(),
+            token: token.clone(),
+            balance,
+            threshold,
+        }
+        .publish(env);
+    }
 }
 
 mod test;
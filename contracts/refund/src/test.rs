@@ -1,7 +1,25 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{testutils::Address as _, testutils::Events, Address, Env, String};
+use soroban_sdk::{
+    testutils::Address as _, testutils::Events, testutils::Ledger, testutils::MockAuth,
+    testutils::MockAuthInvoke, token, Address, Env, IntoVal, String, Symbol, TryFromVal,
+};
+
+fn create_token(env: &Env, holder: &Address, amount: i128) -> Address {
+    let issuer = Address::generate(env);
+    let token = env.register_stellar_asset_contract_v2(issuer).address();
+    token::StellarAssetClient::new(env, &token).mint(holder, &amount);
+    token
+}
+
+fn has_event(env: &Env, contract_id: &Address, name: &str) -> bool {
+    let name = Symbol::new(env, name);
+    env.events().all().iter().any(|(contract, topics, _)| {
+        contract == *contract_id
+            && Symbol::try_from_val(env, &topics.get(0).unwrap()).ok() == Some(name.clone())
+    })
+}
 
 #[test]
 fn test_request_refund_with_valid_data() {
@@ -303,6 +321,7 @@ fn test_approve_requested_refund_successfully() {
     let reason = String::from_str(&env, "Test reason");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -329,6 +348,7 @@ fn test_reject_requested_refund_successfully() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -350,6 +370,7 @@ fn test_approve_nonexistent_refund_should_fail() {
     let nonexistent_refund_id = 999u64;
 
     env.mock_all_auths();
+    client.initialize(&admin);
     client.approve_refund(&admin, &nonexistent_refund_id);
 }
 
@@ -365,6 +386,7 @@ fn test_reject_nonexistent_refund_should_fail() {
     let nonexistent_refund_id = 999u64;
 
     env.mock_all_auths();
+    client.initialize(&admin);
     client.reject_refund(&admin, &nonexistent_refund_id, &rejection_reason);
 }
 
@@ -384,6 +406,7 @@ fn test_approve_already_approved_refund_should_fail() {
     let reason = String::from_str(&env, "Test reason");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -409,6 +432,7 @@ fn test_reject_already_rejected_refund_should_fail() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -434,6 +458,7 @@ fn test_approve_rejected_refund_should_fail() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -459,6 +484,7 @@ fn test_reject_approved_refund_should_fail() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -482,6 +508,7 @@ fn test_refund_approved_event_is_emitted() {
     let reason = String::from_str(&env, "Test reason");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -489,7 +516,7 @@ fn test_refund_approved_event_is_emitted() {
     client.approve_refund(&admin, &refund_id);
 
     let events = env.events().all();
-    assert!(events.len() > 0);
+    assert!(!events.is_empty());
 }
 
 #[test]
@@ -508,6 +535,7 @@ fn test_refund_rejected_event_is_emitted() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -515,7 +543,7 @@ fn test_refund_rejected_event_is_emitted() {
     client.reject_refund(&admin, &refund_id, &rejection_reason);
 
     let events = env.events().all();
-    assert!(events.len() > 0);
+    assert!(!events.is_empty());
 }
 
 #[test]
@@ -533,6 +561,7 @@ fn test_approve_correct_refund_among_multiple() {
     let reason = String::from_str(&env, "Test reason");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id1 = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -568,6 +597,7 @@ fn test_reject_refund_with_empty_reason() {
     let rejection_reason = String::from_str(&env, "");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -594,6 +624,7 @@ fn test_reject_refund_with_detailed_reason() {
     let rejection_reason = String::from_str(&env, "Insufficient evidence provided by merchant");
 
     env.mock_all_auths();
+    client.initialize(&admin);
 
     let refund_id = client
         .request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
@@ -603,3 +634,1100 @@ fn test_reject_refund_with_detailed_reason() {
     let refund = client.get_refund(&refund_id);
     assert_eq!(refund.status, RefundStatus::Rejected);
 }
+
+// Test reserve and process_refund functionality
+#[test]
+fn test_deposit_reserve_increases_balance() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &merchant, 5000);
+
+    let balance = client.deposit_reserve(&merchant, &token, &3000);
+
+    assert_eq!(balance, 3000);
+    assert_eq!(client.get_reserve(&merchant, &token), 3000);
+    assert_eq!(token::Client::new(&env, &token).balance(&contract_id), 3000);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 2000);
+}
+
+#[test]
+#[should_panic]
+fn test_deposit_reserve_with_zero_amount_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &merchant, 5000);
+
+    client.deposit_reserve(&merchant, &token, &0);
+}
+
+#[test]
+fn test_withdraw_reserve_above_commitments() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    client.request_refund(&merchant, &1, &customer, &600, &token, &reason);
+    assert_eq!(client.get_reserve_committed(&merchant, &token), 600);
+
+    // Only the uncommitted 400 can leave the reserve
+    let result = client.try_withdraw_reserve(&merchant, &token, &500);
+    assert_eq!(result, Err(Ok(Error::InsufficientReserve)));
+
+    let balance = client.withdraw_reserve(&merchant, &token, &400);
+    assert_eq!(balance, 600);
+    assert_eq!(token::Client::new(&env, &token).balance(&merchant), 400);
+}
+
+#[test]
+fn test_rejected_refund_releases_commitment() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &600, &token, &reason);
+    client.reject_refund(&admin, &refund_id, &reason);
+
+    assert_eq!(client.get_reserve_committed(&merchant, &token), 0);
+    assert_eq!(client.withdraw_reserve(&merchant, &token, &1000), 0);
+}
+
+#[test]
+fn test_process_refund_debits_reserve_and_pays_customer() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+    client.process_refund(&admin, &refund_id);
+
+    let refund = client.get_refund(&refund_id);
+    assert_eq!(refund.status, RefundStatus::Processed);
+    assert_eq!(client.get_reserve(&merchant, &token), 300);
+    assert_eq!(client.get_reserve_committed(&merchant, &token), 0);
+    assert_eq!(token::Client::new(&env, &token).balance(&customer), 700);
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_process_refund_by_non_admin_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+
+    // Only the stranger signs, so the stored admin's authorization is missing
+    env.mock_auths(&[MockAuth {
+        address: &stranger,
+        invoke: &MockAuthInvoke {
+            contract: &contract_id,
+            fn_name: "process_refund",
+            args: (admin.clone(), refund_id).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.process_refund(&admin, &refund_id);
+}
+
+#[test]
+fn test_process_refund_by_other_signer_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+
+    // A valid signature from an address other than the stored admin is rejected
+    let result = client.try_approve_refund(&stranger, &refund_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+    client.approve_refund(&admin, &refund_id);
+    let result = client.try_process_refund(&stranger, &refund_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.get_refund(&refund_id).status, RefundStatus::Approved);
+}
+
+#[test]
+fn test_admin_actions_require_initialization() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+    let result = client.try_approve_refund(&admin, &refund_id);
+    assert_eq!(result, Err(Ok(Error::NotInitialized)));
+
+    client.initialize(&admin);
+    let result = client.try_initialize(&admin);
+    assert_eq!(result, Err(Ok(Error::AlreadyInitialized)));
+    client.approve_refund(&admin, &refund_id);
+}
+
+#[test]
+fn test_process_refund_with_insufficient_reserve_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &500);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+
+    let result = client.try_process_refund(&admin, &refund_id);
+    assert_eq!(result, Err(Ok(Error::InsufficientReserve)));
+
+    let refund = client.get_refund(&refund_id);
+    assert_eq!(refund.status, RefundStatus::Approved);
+}
+
+#[test]
+fn test_process_requested_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+
+    let result = client.try_process_refund(&admin, &refund_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_process_already_processed_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &300, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+    client.process_refund(&admin, &refund_id);
+
+    let result = client.try_process_refund(&admin, &refund_id);
+    assert_eq!(result, Err(Ok(Error::AlreadyProcessed)));
+}
+
+#[test]
+fn test_reserve_low_balance_event_is_emitted() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    client.set_reserve_threshold(&merchant, &token, &500);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &700, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+    client.process_refund(&admin, &refund_id);
+
+    assert!(has_event(&env, &contract_id, "reserve_low_balance"));
+}
+
+#[test]
+fn test_reserve_low_balance_event_not_emitted_above_threshold() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let token = create_token(&env, &merchant, 1000);
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    client.set_reserve_threshold(&merchant, &token, &500);
+    client.withdraw_reserve(&merchant, &token, &400);
+
+    assert!(!has_event(&env, &contract_id, "reserve_low_balance"));
+}
+
+// Test cancel_refund functionality
+#[test]
+fn test_cancel_requested_refund_successfully() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let payment_id = 1u64;
+    let amount = 1000i128;
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id =
+        client.request_refund(&merchant, &payment_id, &customer, &amount, &token, &reason);
+    assert_eq!(client.get_payment_refund_total(&payment_id), amount);
+
+    client.cancel_refund(&merchant, &refund_id);
+
+    let refund = client.get_refund(&refund_id);
+    assert_eq!(refund.status, RefundStatus::Cancelled);
+    assert_eq!(client.get_payment_refund_total(&payment_id), 0);
+    assert_eq!(client.get_reserve_committed(&merchant, &token), 0);
+}
+
+#[test]
+fn test_cancel_refund_by_other_merchant_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let other_merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+
+    let result = client.try_cancel_refund(&other_merchant, &refund_id);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_cancel_approved_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+
+    let result = client.try_cancel_refund(&merchant, &refund_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_cancel_nonexistent_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+
+    env.mock_all_auths();
+
+    let result = client.try_cancel_refund(&merchant, &999);
+    assert_eq!(result, Err(Ok(Error::RefundNotFound)));
+}
+
+#[test]
+fn test_cancelled_refund_cannot_be_approved() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.cancel_refund(&merchant, &refund_id);
+
+    let result = client.try_approve_refund(&admin, &refund_id);
+    assert_eq!(result, Err(Ok(Error::InvalidStatus)));
+}
+
+#[test]
+fn test_payment_refund_total_tracks_active_refunds() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let payment_id = 7u64;
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id1 =
+        client.request_refund(&merchant, &payment_id, &customer, &300, &token, &reason);
+    let refund_id2 =
+        client.request_refund(&merchant, &payment_id, &customer, &200, &token, &reason);
+    let refund_id3 =
+        client.request_refund(&merchant, &payment_id, &customer, &100, &token, &reason);
+    assert_eq!(client.get_payment_refund_total(&payment_id), 600);
+
+    client.approve_refund(&admin, &refund_id1);
+    client.reject_refund(&admin, &refund_id2, &reason);
+    client.cancel_refund(&merchant, &refund_id3);
+
+    assert_eq!(client.get_payment_refund_total(&payment_id), 300);
+}
+
+#[test]
+fn test_refund_cancelled_event_is_emitted() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.cancel_refund(&merchant, &refund_id);
+
+    assert!(has_event(&env, &contract_id, "refund_cancelled"));
+}
+
+// Test refund destination functionality
+#[test]
+fn test_customer_updates_refund_destination() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let new_wallet = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    assert_eq!(client.get_refund(&refund_id).destination, None);
+
+    client.update_refund_destination(&customer, &refund_id, &new_wallet);
+
+    let refund = client.get_refund(&refund_id);
+    assert_eq!(refund.destination, Some(new_wallet));
+}
+
+#[test]
+fn test_update_refund_destination_by_non_customer_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+
+    let result = client.try_update_refund_destination(&merchant, &refund_id, &merchant);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+}
+
+#[test]
+fn test_update_refund_destination_to_customer_clears_it() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let new_wallet = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.update_refund_destination(&customer, &refund_id, &new_wallet);
+    client.update_refund_destination(&customer, &refund_id, &customer);
+
+    assert_eq!(client.get_refund(&refund_id).destination, None);
+}
+
+#[test]
+fn test_admin_override_destination_records_justification() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let new_wallet = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+    let justification = String::from_str(&env, "Customer verified identity via support ticket");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.override_refund_destination(&admin, &refund_id, &new_wallet, &justification);
+    assert!(has_event(&env, &contract_id, "refund_destination_updated"));
+
+    assert_eq!(
+        client.get_refund(&refund_id).destination,
+        Some(new_wallet.clone())
+    );
+
+    let record = client.get_destination_override(&refund_id).unwrap();
+    assert_eq!(record.admin, admin);
+    assert_eq!(record.destination, new_wallet);
+    assert_eq!(record.justification, justification);
+}
+
+#[test]
+fn test_admin_override_destination_without_justification_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let new_wallet = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+
+    let result = client.try_override_refund_destination(
+        &admin,
+        &refund_id,
+        &new_wallet,
+        &String::from_str(&env, ""),
+    );
+    assert_eq!(result, Err(Ok(Error::JustificationRequired)));
+    assert!(client.get_destination_override(&refund_id).is_none());
+}
+
+#[test]
+fn test_override_destination_by_non_admin_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let thief = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+    let justification = String::from_str(&env, "x");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+
+    // Signing as a self-declared admin is not enough to redirect the refund
+    let result = client.try_override_refund_destination(&thief, &refund_id, &thief, &justification);
+    assert_eq!(result, Err(Ok(Error::Unauthorized)));
+    assert_eq!(client.get_refund(&refund_id).destination, None);
+}
+
+#[test]
+fn test_customer_update_supersedes_admin_override() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let support_wallet = Address::generate(&env);
+    let own_wallet = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+    let justification = String::from_str(&env, "Customer lost access to their wallet");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.override_refund_destination(&admin, &refund_id, &support_wallet, &justification);
+    assert!(client.get_destination_override(&refund_id).is_some());
+
+    client.update_refund_destination(&customer, &refund_id, &own_wallet);
+    assert_eq!(client.get_refund(&refund_id).destination, Some(own_wallet));
+    assert!(client.get_destination_override(&refund_id).is_none());
+}
+
+#[test]
+fn test_update_destination_of_processed_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let new_wallet = Address::generate(&env);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &1000, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+    client.process_refund(&admin, &refund_id);
+
+    let result = client.try_update_refund_destination(&customer, &refund_id, &new_wallet);
+    assert_eq!(result, Err(Ok(Error::AlreadyProcessed)));
+}
+
+#[test]
+fn test_process_refund_pays_destination() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let new_wallet = Address::generate(&env);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Test reason");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &600, &token, &reason);
+    client.approve_refund(&admin, &refund_id);
+    client.update_refund_destination(&customer, &refund_id, &new_wallet);
+    client.process_refund(&admin, &refund_id);
+
+    let token_client = token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&new_wallet), 600);
+    assert_eq!(token_client.balance(&customer), 0);
+}
+
+// Test refund listing and counters
+#[test]
+fn test_list_refunds_by_payment() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    let refund_id1 = client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    client.request_refund(&merchant, &2, &customer, &200, &token, &reason);
+    let refund_id3 = client.request_refund(&merchant, &1, &customer, &300, &token, &reason);
+
+    let refunds = client.list_refunds_by_payment(&1, &0, &10);
+    assert_eq!(refunds.len(), 2);
+    assert_eq!(refunds.get(0).unwrap().id, refund_id1);
+    assert_eq!(refunds.get(1).unwrap().id, refund_id3);
+    assert_eq!(client.list_refunds_by_payment(&99, &0, &10).len(), 0);
+
+    // The cursor counts the entries already read for this payment
+    let refunds = client.list_refunds_by_payment(&1, &0, &1);
+    assert_eq!(refunds.len(), 1);
+    assert_eq!(refunds.get(0).unwrap().id, refund_id1);
+    let refunds = client.list_refunds_by_payment(&1, &1, &1);
+    assert_eq!(refunds.get(0).unwrap().id, refund_id3);
+}
+
+#[test]
+fn test_list_refunds_by_payment_caps_page_size() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    for _ in 0..MAX_PAGE_SIZE + 1 {
+        client.request_refund(&merchant, &1, &customer, &1, &token, &reason);
+    }
+
+    let refunds = client.list_refunds_by_payment(&1, &0, &u32::MAX);
+    assert_eq!(refunds.len(), MAX_PAGE_SIZE);
+}
+
+#[test]
+fn test_list_refunds_by_merchant_with_status_filter() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let other_merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id1 = client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    let refund_id2 = client.request_refund(&merchant, &2, &customer, &200, &token, &reason);
+    client.request_refund(&other_merchant, &3, &customer, &300, &token, &reason);
+    client.approve_refund(&admin, &refund_id2);
+
+    let all = client.list_refunds_by_merchant(&merchant, &None, &0, &10);
+    assert_eq!(all.len(), 2);
+
+    let approved =
+        client.list_refunds_by_merchant(&merchant, &Some(RefundStatus::Approved), &0, &10);
+    assert_eq!(approved.len(), 1);
+    assert_eq!(approved.get(0).unwrap().id, refund_id2);
+
+    let requested =
+        client.list_refunds_by_merchant(&merchant, &Some(RefundStatus::Requested), &0, &10);
+    assert_eq!(requested.len(), 1);
+    assert_eq!(requested.get(0).unwrap().id, refund_id1);
+}
+
+#[test]
+fn test_list_refunds_by_merchant_paginates_with_cursor() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    for payment_id in 1..=5u64 {
+        client.request_refund(&merchant, &payment_id, &customer, &100, &token, &reason);
+    }
+
+    let page1 = client.list_refunds_by_merchant(&merchant, &None, &0, &2);
+    assert_eq!(page1.len(), 2);
+    assert_eq!(page1.get(1).unwrap().id, 2);
+
+    let page2 = client.list_refunds_by_merchant(&merchant, &None, &2, &2);
+    assert_eq!(page2.len(), 2);
+    assert_eq!(page2.get(0).unwrap().id, 3);
+
+    let page3 = client.list_refunds_by_merchant(&merchant, &None, &4, &2);
+    assert_eq!(page3.len(), 1);
+    assert_eq!(page3.get(0).unwrap().id, 5);
+}
+
+#[test]
+fn test_filtered_merchant_page_reads_at_most_limit_entries() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    for payment_id in 1..=3u64 {
+        client.request_refund(&merchant, &payment_id, &customer, &100, &token, &reason);
+    }
+    client.approve_refund(&admin, &3);
+
+    // The first page only reads the two requested refunds, so it comes back empty
+    let approved = Some(RefundStatus::Approved);
+    let page1 = client.list_refunds_by_merchant(&merchant, &approved, &0, &2);
+    assert_eq!(page1.len(), 0);
+
+    let page2 = client.list_refunds_by_merchant(&merchant, &approved, &2, &2);
+    assert_eq!(page2.len(), 1);
+    assert_eq!(page2.get(0).unwrap().id, 3);
+}
+
+#[test]
+fn test_list_refunds_by_customer() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let other_customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    client.request_refund(&merchant, &2, &other_customer, &200, &token, &reason);
+    client.request_refund(&merchant, &3, &customer, &300, &token, &reason);
+
+    let refunds = client.list_refunds_by_customer(&customer, &0, &10);
+    assert_eq!(refunds.len(), 2);
+    assert_eq!(refunds.get(0).unwrap().payment_id, 1);
+    assert_eq!(refunds.get(1).unwrap().payment_id, 3);
+}
+
+#[test]
+fn test_refund_counts_follow_transitions() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let other_merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id1 = client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    let refund_id2 = client.request_refund(&merchant, &2, &customer, &200, &token, &reason);
+    let refund_id3 = client.request_refund(&other_merchant, &3, &customer, &300, &token, &reason);
+    assert_eq!(client.get_refund_count(&RefundStatus::Requested), 3);
+
+    client.approve_refund(&admin, &refund_id1);
+    client.reject_refund(&admin, &refund_id2, &reason);
+    client.cancel_refund(&other_merchant, &refund_id3);
+
+    assert_eq!(client.get_refund_count(&RefundStatus::Requested), 0);
+    assert_eq!(client.get_refund_count(&RefundStatus::Approved), 1);
+    assert_eq!(client.get_refund_count(&RefundStatus::Rejected), 1);
+    assert_eq!(client.get_refund_count(&RefundStatus::Cancelled), 1);
+    assert_eq!(
+        client.get_merchant_refund_count(&merchant, &RefundStatus::Approved),
+        1
+    );
+    assert_eq!(
+        client.get_merchant_refund_count(&merchant, &RefundStatus::Cancelled),
+        0
+    );
+}
+
+#[test]
+fn test_list_refunds_limit_is_capped() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+
+    for payment_id in 1..=(MAX_PAGE_SIZE as u64 + 5) {
+        client.request_refund(&merchant, &payment_id, &customer, &100, &token, &reason);
+    }
+
+    let refunds = client.list_refunds_by_merchant(&merchant, &None, &0, &u32::MAX);
+    assert_eq!(refunds.len(), MAX_PAGE_SIZE);
+}
+
+// Test refund history
+#[test]
+fn test_refund_history_records_each_transition() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    env.mock_all_auths();
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    client.initialize(&admin);
+    let token = create_token(&env, &merchant, 1000);
+    let reason = String::from_str(&env, "Damaged on arrival");
+
+    client.deposit_reserve(&merchant, &token, &1000);
+
+    env.ledger().set_timestamp(100);
+    let refund_id = client.request_refund(&merchant, &1, &customer, &500, &token, &reason);
+    env.ledger().set_timestamp(200);
+    client.approve_refund(&admin, &refund_id);
+    env.ledger().set_timestamp(300);
+    client.process_refund(&admin, &refund_id);
+
+    let history = client.get_refund_history(&refund_id);
+    assert_eq!(history.len(), 3);
+
+    let requested = history.get(0).unwrap();
+    assert_eq!(requested.status, RefundStatus::Requested);
+    assert_eq!(requested.actor, merchant);
+    assert_eq!(requested.timestamp, 100);
+    assert_eq!(requested.note, reason);
+
+    let approved = history.get(1).unwrap();
+    assert_eq!(approved.status, RefundStatus::Approved);
+    assert_eq!(approved.actor, admin);
+    assert_eq!(approved.timestamp, 200);
+
+    let processed = history.get(2).unwrap();
+    assert_eq!(processed.status, RefundStatus::Processed);
+    assert_eq!(processed.timestamp, 300);
+    assert_eq!(processed.sequence, env.ledger().sequence());
+
+    let refund = client.get_refund(&refund_id);
+    assert_eq!(refund.requested_at, 100);
+    assert_eq!(refund.approved_at, Some(200));
+    assert_eq!(refund.rejected_at, None);
+    assert_eq!(refund.processed_at, Some(300));
+}
+
+#[test]
+fn test_refund_history_records_rejection_reason() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let token = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+    let rejection_reason = String::from_str(&env, "Outside refund window");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &500, &token, &reason);
+    env.ledger().set_timestamp(50);
+    client.reject_refund(&admin, &refund_id, &rejection_reason);
+
+    let history = client.get_refund_history(&refund_id);
+    assert_eq!(history.len(), 2);
+
+    let rejected = history.get(1).unwrap();
+    assert_eq!(rejected.status, RefundStatus::Rejected);
+    assert_eq!(rejected.actor, admin);
+    assert_eq!(rejected.note, rejection_reason);
+    assert_eq!(client.get_refund(&refund_id).rejected_at, Some(50));
+}
+
+#[test]
+fn test_get_history_of_nonexistent_refund_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let result = client.try_get_refund_history(&999);
+    assert_eq!(result, Err(Ok(Error::RefundNotFound)));
+}
+
+#[test]
+fn test_batch_approve_refunds_reports_each_result() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let first = client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    let second = client.request_refund(&merchant, &2, &customer, &200, &token, &reason);
+    client.reject_refund(&admin, &second, &String::from_str(&env, "No"));
+
+    let ids = Vec::from_array(&env, [first, second, 999]);
+    let results = client.batch_approve_refunds(&admin, &ids);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        results.get(0).unwrap(),
+        BatchResult {
+            id: first,
+            success: true,
+            error_code: 0
+        }
+    );
+    assert_eq!(
+        results.get(1).unwrap().error_code,
+        Error::InvalidStatus as u32
+    );
+    assert_eq!(
+        results.get(2).unwrap().error_code,
+        Error::RefundNotFound as u32
+    );
+    assert_eq!(client.get_refund(&first).status, RefundStatus::Approved);
+    assert_eq!(client.get_refund(&second).status, RefundStatus::Rejected);
+
+    let too_many = Vec::from_array(&env, [0u64; 51]);
+    let result = client.try_batch_approve_refunds(&admin, &too_many);
+    assert_eq!(result.err(), Some(Ok(Error::BatchTooLarge)));
+}
+
+#[test]
+#[should_panic(expected = "Error(Auth, InvalidAction)")]
+fn test_batch_approve_refunds_by_non_admin_should_fail() {
+    let env = Env::default();
+    let contract_id = env.register(RefundContract, ());
+    let client = RefundContractClient::new(&env, &contract_id);
+
+    let merchant = Address::generate(&env);
+    let customer = Address::generate(&env);
+    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let reason = String::from_str(&env, "Test reason");
+
+    env.mock_all_auths();
+    client.initialize(&admin);
+
+    let refund_id = client.request_refund(&merchant, &1, &customer, &100, &token, &reason);
+    let ids = Vec::from_array(&env, [refund_id]);
+
+    // Only the merchant signs, so the stored admin's authorization is missing
+    env.mock_auths(&[MockAuth {
+        address: &merchant,
+        invoke: &MockAuthInvoke {
+            contract: &contract_id,
+            fn_name: "batch_approve_refunds",
+            args: (admin.clone(), ids.clone()).into_val(&env),
+            sub_invokes: &[],
+        },
+    }]);
+    client.batch_approve_refunds(&admin, &ids);
+}